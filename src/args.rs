@@ -0,0 +1,134 @@
+//! Command line flags. The web build doesn't get any.
+
+use anyhow::{anyhow, bail, Context};
+use macroquad::prelude::warn;
+use once_cell::sync::Lazy;
+
+use std::{
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    controls::{InputRecording, InputSubscriber},
+    utils::profile::Profile,
+};
+
+const USAGE: &str = "flags:
+  --record <file>           write every input to <file>
+  --replay <file>           play back a file made with --record
+  --export-profile <file>   write your profile to <file> as a code, then quit
+  --import-profile <file>   replace your profile with the code in <file>, then quit";
+
+/// The flags the game was started with. Bad flags get warned about and ignored.
+pub static ARGS: Lazy<Args> = Lazy::new(|| match Args::from_env() {
+    Ok(args) => args,
+    Err(oh_no) => {
+        warn!("{:?}", oh_no);
+        Args::default()
+    }
+});
+
+#[derive(Debug, Default)]
+pub struct Args {
+    /// Write every input to this file
+    pub record: Option<PathBuf>,
+    /// Play back this file instead of listening to the player
+    pub replay: Option<PathBuf>,
+    /// Write the profile code here
+    pub export_profile: Option<PathBuf>,
+    /// Read a profile code from here
+    pub import_profile: Option<PathBuf>,
+}
+
+impl Args {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> anyhow::Result<Self> {
+        let mut out = Self::default();
+        let mut args = args.into_iter();
+        while let Some(flag) = args.next() {
+            let slot = match flag.as_str() {
+                "--record" => &mut out.record,
+                "--replay" => &mut out.replay,
+                "--export-profile" => &mut out.export_profile,
+                "--import-profile" => &mut out.import_profile,
+                _ => bail!("unknown flag `{}`\n{}", flag, USAGE),
+            };
+            let path = args
+                .next()
+                .ok_or_else(|| anyhow!("`{}` needs a file\n{}", flag, USAGE))?;
+            *slot = Some(path.into());
+        }
+        Ok(out)
+    }
+
+    /// Hook up recording and replaying.
+    ///
+    /// If something goes wrong the game still runs, just without them.
+    pub fn setup_controls(&self, controls: &mut InputSubscriber) {
+        if let Some(path) = &self.replay {
+            match InputRecording::load(path) {
+                Ok(recording) => controls.start_replay(recording),
+                Err(oh_no) => warn!("Couldn't start the replay!\n{:?}", oh_no),
+            }
+        }
+        if let Some(path) = &self.record {
+            if let Err(oh_no) = controls.record_to(path) {
+                warn!("Couldn't start recording!\n{:?}", oh_no);
+            }
+        }
+    }
+
+    /// Export or import the profile if the flags say to.
+    ///
+    /// Returns `true` if it did, and the game should quit instead of starting.
+    pub fn run_profile_commands(&self) -> bool {
+        let mut ran = false;
+        if let Some(path) = &self.export_profile {
+            match export_profile(path) {
+                Ok(()) => println!("Wrote your profile to {}", path.display()),
+                Err(oh_no) => eprintln!("Couldn't export your profile!\n{:?}", oh_no),
+            }
+            ran = true;
+        }
+        if let Some(path) = &self.import_profile {
+            if let Err(oh_no) = import_profile(path) {
+                eprintln!("Couldn't import your profile!\n{:?}", oh_no);
+            }
+            ran = true;
+        }
+        ran
+    }
+}
+
+fn export_profile(path: &Path) -> anyhow::Result<()> {
+    let code = Profile::current().export()?;
+    std::fs::write(path, code).with_context(|| format!("couldn't write {}", path.display()))
+}
+
+/// Show what importing would change and ask before doing it.
+fn import_profile(path: &Path) -> anyhow::Result<()> {
+    let code = std::fs::read_to_string(path)
+        .with_context(|| format!("couldn't read {}", path.display()))?;
+    let profile = Profile::import(&code)?;
+
+    println!("Importing would change:");
+    for change in Profile::current().describe_changes(&profile) {
+        println!("  {}", change);
+    }
+    print!("Replace your profile? [y/N] ");
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+
+    if answer.trim().eq_ignore_ascii_case("y") {
+        Profile::current().replace(profile);
+        println!("Imported!");
+    } else {
+        println!("Kept your profile.");
+    }
+    Ok(())
+}
@@ -1,39 +1,131 @@
 #![allow(clippy::eval_order_dependence)]
 
+use ahash::AHashMap;
 use macroquad::{
-    audio::{load_sound, Sound},
+    audio::{load_sound, PlaySoundParams, Sound},
     miniquad::*,
     prelude::*,
 };
 use once_cell::sync::Lazy;
 
-use std::path::PathBuf;
+use std::{
+    path::PathBuf,
+    sync::{Mutex, RwLock, RwLockReadGuard},
+};
 
 use crate::simulator::{
     levels::{Level, RawLevel},
     symbols::Symbol,
 };
 
-pub struct Assets {
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+pub mod reload;
+
+/// Assets the game logic needs, which load without a window or a GPU.
+///
+/// Gamemodes only get these when updating, so they can run headless.
+pub struct LogicAssets {
+    /// Locked so debug builds can swap in levels that changed on disk
+    levels: RwLock<Vec<Level>>,
+    /// Sounds the logic wants played, waiting for the draw loop to get to them
+    pub sounds: SoundQueue,
+    /// Levels that got reloaded since the game started
+    pub reloads: Reloads,
+}
+
+impl LogicAssets {
+    pub async fn init() -> Self {
+        Self {
+            levels: RwLock::new(levels().await),
+            sounds: SoundQueue::default(),
+            reloads: Reloads::default(),
+        }
+    }
+
+    pub fn levels(&self) -> RwLockReadGuard<'_, Vec<Level>> {
+        self.levels.read().unwrap()
+    }
+
+    /// Load straight off the disk without going through macroquad,
+    /// which needs a window to load things.
+    #[cfg(test)]
+    pub fn init_blocking() -> anyhow::Result<Self> {
+        let read = |path: PathBuf| std::fs::read_to_string(path);
+        let manifest = read(ASSETS_ROOT.join("levels/manifest.toml"))?;
+        let levels = level_paths(&manifest)?
+            .into_iter()
+            .map(|(stub, path)| level(stub, &read(path)?))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            levels: RwLock::new(levels),
+            sounds: SoundQueue::default(),
+            reloads: Reloads::default(),
+        })
+    }
+}
+
+/// Assets that are only for showing the game to the player: textures and sounds.
+pub struct RenderAssets {
     pub textures: Textures,
-    pub sounds: Sounds,
-    pub levels: Vec<Level>,
+    /// Locked so debug builds can swap in sounds that changed on disk
+    sounds: Mutex<Sounds>,
 
     /// Global symbol atlas. Yes global mutability bad shut up
     pub symbol_atlas: Texture2D,
+    /// Which symbols are stitched into the atlas right now, and where
+    stitched: Mutex<AHashMap<u32, usize>>,
+    /// Scratch canvases for drawing both sides of a transition effect
+    pub effect_canvases: [RenderTarget; 2],
 }
 
-impl Assets {
+impl RenderAssets {
     pub async fn init() -> Self {
         let symbol_atlas = Image::gen_image_color(Symbol::ATLAS_SIDE, Symbol::ATLAS_SIDE, BLANK);
         let symbol_atlas = Texture2D::from_image(&symbol_atlas);
         symbol_atlas.set_filter(FilterMode::Nearest);
 
+        let effect_canvas = || {
+            let canvas = render_target(crate::WIDTH as u32, crate::HEIGHT as u32);
+            canvas.texture.set_filter(FilterMode::Nearest);
+            canvas
+        };
+
         Self {
             textures: Textures::init().await,
-            sounds: Sounds::init().await,
-            levels: levels().await,
+            sounds: Mutex::new(Sounds::init().await),
             symbol_atlas,
+            stitched: Mutex::new(AHashMap::new()),
+            effect_canvases: [effect_canvas(), effect_canvas()],
+        }
+    }
+
+    /// Make sure the symbol atlas has these symbols at these indices,
+    /// redrawing it if it doesn't.
+    pub fn stitch_atlas(&self, indices: &AHashMap<u32, usize>) {
+        let mut stitched = self.stitched.lock().unwrap();
+        if *stitched != *indices {
+            self.symbol_atlas.update(&Symbol::atlas_image(indices));
+            *stitched = indices.clone();
+        }
+    }
+
+    /// Start and stop whatever sounds the logic asked for since last time.
+    pub fn play_sounds(&self, queue: &SoundQueue) {
+        let sounds = self.sounds.lock().unwrap();
+        for cue in queue.take() {
+            match cue {
+                SoundCue::Play { sound, volume } => {
+                    macroquad::audio::play_sound(
+                        sounds.get(sound),
+                        PlaySoundParams {
+                            looped: false,
+                            volume,
+                        },
+                    );
+                }
+                SoundCue::Stop(sound) => macroquad::audio::stop_sound(sounds.get(sound)),
+            }
         }
     }
 }
@@ -84,6 +176,98 @@ impl Sounds {
             title_jingle: sound("title/jingle").await,
         }
     }
+
+    fn get(&self, id: SoundId) -> Sound {
+        match id {
+            SoundId::TitleJingle => self.title_jingle,
+        }
+    }
+}
+
+/// Names for sounds, so the logic can ask for them without loading them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SoundId {
+    TitleJingle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SoundCue {
+    Play {
+        sound: SoundId,
+        /// From 0 to 1
+        volume: f32,
+    },
+    Stop(SoundId),
+}
+
+/// Sounds to start and stop, from the update thread to the draw thread.
+#[derive(Debug, Default)]
+pub struct SoundQueue {
+    cues: Mutex<Vec<SoundCue>>,
+}
+
+impl SoundQueue {
+    pub fn play(&self, sound: SoundId, volume: f32) {
+        self.cues
+            .lock()
+            .unwrap()
+            .push(SoundCue::Play { sound, volume });
+    }
+
+    pub fn stop(&self, sound: SoundId) {
+        self.cues.lock().unwrap().push(SoundCue::Stop(sound));
+    }
+
+    /// Take out every cue queued so far.
+    pub fn take(&self) -> Vec<SoundCue> {
+        std::mem::take(&mut *self.cues.lock().unwrap())
+    }
+}
+
+/// Levels that changed on disk while the game was running.
+///
+/// Only debug builds watch for changes, so otherwise this stays empty.
+#[derive(Debug, Default)]
+pub struct Reloads {
+    log: Mutex<ReloadLog>,
+}
+
+#[derive(Debug, Default)]
+struct ReloadLog {
+    /// Goes up by one every time a level gets reloaded
+    count: u32,
+    /// For each level that got reloaded, the count when it last was,
+    /// and what went wrong if it couldn't be loaded
+    levels: AHashMap<String, (u32, Option<String>)>,
+}
+
+impl Reloads {
+    /// How many reloads there have been so far.
+    pub fn count(&self) -> u32 {
+        self.log.lock().unwrap().count
+    }
+
+    /// Has the level with this ID been reloaded since the count was `seen`?
+    ///
+    /// Catches `seen` up, and returns whether the new version loaded all right.
+    pub fn check(&self, id: &str, seen: &mut u32) -> Option<Result<(), String>> {
+        let log = self.log.lock().unwrap();
+        let last_seen = std::mem::replace(seen, log.count);
+        let (when, problem) = log.levels.get(id)?;
+        (*when > last_seen).then(|| match problem {
+            Some(problem) => Err(problem.clone()),
+            None => Ok(()),
+        })
+    }
+
+    /// Write down that a level got reloaded. Only debug builds ever do.
+    #[allow(dead_code)]
+    fn record(&self, id: String, problem: Option<String>) {
+        let mut log = self.log.lock().unwrap();
+        log.count += 1;
+        let count = log.count;
+        log.levels.insert(id, (count, problem));
+    }
 }
 
 /// Path to the assets root
@@ -153,27 +337,41 @@ async fn material(path_stub: &str, params: MaterialParams) -> Material {
 }
 
 async fn levels() -> Vec<Level> {
-    #[derive(serde::Deserialize)]
-    struct Manifest {
-        levels: Vec<String>,
-    }
-
     let manifest_string = load_string(&ASSETS_ROOT.join("levels/manifest.toml").to_string_lossy())
         .await
         .unwrap();
-    let manifest: Manifest = toml::from_str(&manifest_string).unwrap();
 
     let mut out = Vec::new();
-    for path_stub in manifest.levels {
-        let path = ASSETS_ROOT
-            .join("levels")
-            .join(&path_stub)
-            .with_extension("toml");
+    for (path_stub, path) in level_paths(&manifest_string).unwrap() {
         let level_string = load_string(&path.to_string_lossy()).await.unwrap();
-        let raw: RawLevel = toml::from_str(&level_string).unwrap();
-        let level = raw.to_level(path_stub).unwrap();
-        out.push(level);
+        out.push(level(path_stub, &level_string).unwrap());
     }
 
     out
 }
+
+/// Read the level manifest into the ID and path of each level, in order.
+fn level_paths(manifest: &str) -> anyhow::Result<Vec<(String, PathBuf)>> {
+    #[derive(serde::Deserialize)]
+    struct Manifest {
+        levels: Vec<String>,
+    }
+
+    let manifest: Manifest = toml::from_str(manifest)?;
+    Ok(manifest
+        .levels
+        .into_iter()
+        .map(|path_stub| {
+            let path = ASSETS_ROOT
+                .join("levels")
+                .join(&path_stub)
+                .with_extension("toml");
+            (path_stub, path)
+        })
+        .collect())
+}
+
+fn level(path_stub: String, level_string: &str) -> anyhow::Result<Level> {
+    let raw: RawLevel = toml::from_str(level_string)?;
+    raw.to_level(path_stub)
+}
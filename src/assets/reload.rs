@@ -0,0 +1,217 @@
+//! Reloading assets that change on disk while the game runs, for debug builds.
+//!
+//! This just looks at when each file was last modified every so often,
+//! which is plenty for the handful of files we've got.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use ahash::AHashMap;
+use macroquad::{
+    audio::{load_sound, stop_sound, Sound},
+    prelude::*,
+};
+
+use super::{level, level_paths, LogicAssets, RenderAssets, Sounds, ASSETS_ROOT};
+
+/// Seconds between looking for changes
+const CHECK_EVERY: f32 = 0.5;
+
+pub struct Watcher {
+    /// When each file under the assets root was last modified, as of the last check
+    modified: AHashMap<PathBuf, SystemTime>,
+    /// Seconds until the next check
+    cooldown: f32,
+}
+
+impl Watcher {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            modified: scan(),
+            cooldown: CHECK_EVERY,
+        }
+    }
+
+    /// Reload anything that changed since last time, if it's time to look again.
+    ///
+    /// This has to run on the main thread, because textures and sounds live there.
+    pub async fn poll(&mut self, dt: f32, logic: &LogicAssets, render: &RenderAssets) {
+        self.cooldown -= dt;
+        if self.cooldown > 0.0 {
+            return;
+        }
+        self.cooldown = CHECK_EVERY;
+
+        let now = scan();
+        let changed: Vec<PathBuf> = now
+            .iter()
+            .filter(|(path, modified)| self.modified.get(*path) != Some(*modified))
+            .map(|(path, _)| path.clone())
+            .collect();
+        self.modified = now;
+
+        let levels_dir = ASSETS_ROOT.join("levels");
+        let textures_dir = ASSETS_ROOT.join("textures");
+        let sounds_dir = ASSETS_ROOT.join("sounds");
+        let mut levels_changed = Vec::new();
+        for path in changed {
+            if path.starts_with(&levels_dir) {
+                levels_changed.push(path);
+            } else if let Ok(stub) = path.strip_prefix(&textures_dir) {
+                reload_texture(&stub_of(stub), &path, render).await;
+            } else if let Ok(stub) = path.strip_prefix(&sounds_dir) {
+                reload_sound(&stub_of(stub), &path, render).await;
+            }
+        }
+        if !levels_changed.is_empty() {
+            reload_levels(&levels_changed, logic);
+        }
+    }
+}
+
+/// Find every file under the assets root and when it was last modified.
+fn scan() -> AHashMap<PathBuf, SystemTime> {
+    let mut out = AHashMap::new();
+    let mut dirs = vec![ASSETS_ROOT.clone()];
+    while let Some(dir) = dirs.pop() {
+        let entries = match fs::read_dir(&dir) {
+            Ok(it) => it,
+            Err(_) => continue,
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            match entry.metadata() {
+                Ok(meta) if meta.is_dir() => dirs.push(path),
+                Ok(meta) => {
+                    if let Ok(modified) = meta.modified() {
+                        out.insert(path, modified);
+                    }
+                }
+                Err(_) => {}
+            }
+        }
+    }
+    out
+}
+
+/// Turn a path under an asset folder into the stub it's loaded by, like `ui/font_small`.
+fn stub_of(path: &Path) -> String {
+    path.with_extension("")
+        .to_string_lossy()
+        .replace(std::path::MAIN_SEPARATOR, "/")
+}
+
+/// Load the levels in these files again.
+///
+/// If the manifest changed, every level in it gets loaded again, to catch new ones.
+fn reload_levels(changed: &[PathBuf], assets: &LogicAssets) {
+    let manifest_path = ASSETS_ROOT.join("levels/manifest.toml");
+    let manifest_changed = changed.contains(&manifest_path);
+    let paths = fs::read_to_string(&manifest_path)
+        .map_err(anyhow::Error::from)
+        .and_then(|manifest| level_paths(&manifest));
+    let paths = match paths {
+        Ok(it) => it,
+        Err(oh_no) => {
+            warn!("Couldn't read the level manifest!\n{:?}", oh_no);
+            return;
+        }
+    };
+
+    for (stub, path) in paths {
+        if !(manifest_changed || changed.contains(&path)) {
+            continue;
+        }
+        let res = fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|src| level(stub.clone(), &src));
+        match res {
+            Ok(new) => {
+                info!("Reloaded level {}", stub);
+                let mut levels = assets.levels.write().unwrap();
+                match levels.iter_mut().find(|level| level.id == new.id) {
+                    Some(old) => *old = new,
+                    None => levels.push(new),
+                }
+                drop(levels);
+                assets.reloads.record(stub, None);
+            }
+            Err(oh_no) => {
+                warn!("Couldn't reload level {}!\n{:?}", stub, oh_no);
+                assets.reloads.record(stub, Some(format!("{:#}", oh_no)));
+            }
+        }
+    }
+}
+
+/// Every texture, along with the stub it's loaded from.
+fn textures(assets: &RenderAssets) -> [(&'static str, Texture2D); 5] {
+    let textures = &assets.textures;
+    [
+        ("ui/font_small", textures.fonts.small),
+        ("ui/font_medium", textures.fonts.medium),
+        ("title/banner", textures.title_banner),
+        ("ui/billboard_patch9", textures.billboard_patch9),
+        ("checkerboard", textures.checkerboard),
+    ]
+}
+
+/// Textures are drawn straight from `RenderAssets` everywhere, so they get updated in place.
+/// That only works when the size stays the same.
+async fn reload_texture(stub: &str, path: &Path, assets: &RenderAssets) {
+    let texture = match textures(assets).iter().find(|(it, _)| *it == stub) {
+        Some((_, texture)) => *texture,
+        None => return,
+    };
+    let image = match load_image(&path.to_string_lossy()).await {
+        Ok(it) => it,
+        Err(oh_no) => {
+            warn!("Couldn't reload texture {}!\n{:?}", stub, oh_no);
+            return;
+        }
+    };
+    if image.width() as f32 != texture.width() || image.height() as f32 != texture.height() {
+        warn!(
+            "Texture {} changed size, so restart to see it ({}x{} -> {}x{})",
+            stub,
+            texture.width(),
+            texture.height(),
+            image.width(),
+            image.height()
+        );
+        return;
+    }
+    texture.update(&image);
+    info!("Reloaded texture {}", stub);
+}
+
+/// Where a sound is kept, by the stub it's loaded from.
+fn sound_slot<'a>(sounds: &'a mut Sounds, stub: &str) -> Option<&'a mut Sound> {
+    match stub {
+        "title/jingle" => Some(&mut sounds.title_jingle),
+        _ => None,
+    }
+}
+
+async fn reload_sound(stub: &str, path: &Path, assets: &RenderAssets) {
+    if sound_slot(&mut assets.sounds.lock().unwrap(), stub).is_none() {
+        return;
+    }
+    let sound = match load_sound(&path.to_string_lossy()).await {
+        Ok(it) => it,
+        Err(oh_no) => {
+            warn!("Couldn't reload sound {}!\n{:?}", stub, oh_no);
+            return;
+        }
+    };
+    let mut sounds = assets.sounds.lock().unwrap();
+    if let Some(slot) = sound_slot(&mut sounds, stub) {
+        stop_sound(*slot);
+        *slot = sound;
+        info!("Reloaded sound {}", stub);
+    }
+}
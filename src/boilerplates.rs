@@ -1,15 +1,40 @@
-use crate::{assets::Assets, controls::InputSubscriber};
+pub mod debug;
+mod effect;
+#[cfg(test)]
+pub mod headless;
+pub use effect::Effect;
+
+use std::{
+    any::Any,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use cogs_gamedev::controls::InputHandler;
+use macroquad::prelude::{draw_rectangle, Color};
+
+use crate::{
+    assets::{LogicAssets, RenderAssets},
+    controls::{Control, InputSubscriber},
+    utils::profile::Profile,
+    HEIGHT, UPDATE_DT, WIDTH,
+};
+
+use self::{debug::DebugDrawer, effect::EffectDrawer};
 
 /// Things the engine can update and draw
-pub trait Gamemode {
+pub trait Gamemode: AsAny {
     /// Update the state.
     ///
     /// Return how to swap to another state if need be.
+    /// This can't touch anything that needs a window, so it can run headless.
     fn update(
         &mut self,
         controls: &InputSubscriber,
         frame_info: FrameInfo,
-        assets: &Assets,
+        assets: &LogicAssets,
     ) -> Transition;
 
     /// Gather information about how to draw this state.
@@ -17,12 +42,65 @@ pub trait Gamemode {
 
     /// When a `Transition` finishes and things are popped off to reveal this gamemode,
     /// this function is called.
-    fn on_resume(&mut self, assets: &Assets) {}
+    fn on_resume(&mut self, assets: &LogicAssets) {}
+
+    /// Does this mode only cover part of the screen?
+    /// If so, the modes under it get drawn first, dimmed.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    /// Lines about what's going on inside, for the debug overlay.
+    fn debug_info(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// How dark the modes under an overlay get
+const OVERLAY_DIM: f32 = 0.6;
+
+/// Set when a mode wants the game closed. Only the draw loop can actually do it.
+static QUIT: AtomicBool = AtomicBool::new(false);
+
+/// Ask for the game to close once this frame's done.
+pub fn request_quit() {
+    QUIT.store(true, Ordering::Relaxed);
+}
+
+pub fn quit_requested() -> bool {
+    QUIT.load(Ordering::Relaxed)
+}
+
+/// Lets gamemodes be downcast, so tests can look inside them.
+pub trait AsAny {
+    #[cfg(test)]
+    fn as_any(&self) -> &dyn Any;
+    #[cfg(test)]
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    /// The name of the type, without the path to it.
+    fn type_name(&self) -> &'static str;
+}
+
+impl<T: Any> AsAny for T {
+    #[cfg(test)]
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    #[cfg(test)]
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn type_name(&self) -> &'static str {
+        let name = std::any::type_name::<T>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
 }
 
 /// Data on how to draw a state
 pub trait GamemodeDrawer: Send {
-    fn draw(&self, assets: &Assets, frame_info: FrameInfo);
+    fn draw(&self, assets: &RenderAssets, frame_info: FrameInfo);
 }
 
 /// Information about a frame.
@@ -36,6 +114,26 @@ pub struct FrameInfo {
     // 0.97 x expected sun lifetime!
     // how exciting.
     pub frames_ran: u64,
+    /// How far it's been from the last update to the next one, from 0 to 1.
+    /// Drawers can push things that move every update along by this much so they look smooth.
+    /// Gamemodes always get 0.
+    pub interpolation: f32,
+}
+
+impl FrameInfo {
+    /// Info for the first update.
+    pub fn first_update() -> Self {
+        Self {
+            dt: UPDATE_DT,
+            frames_ran: 0,
+            interpolation: 0.0,
+        }
+    }
+
+    /// Seconds of update time since the last update, going by `interpolation`.
+    pub fn since_update(&self) -> f32 {
+        self.interpolation * UPDATE_DT
+    }
 }
 /// Ways modes can transition
 #[allow(dead_code)]
@@ -51,13 +149,32 @@ pub enum Transition {
     /// The most customizable: pop N entries off the stack, then push some new ones.
     /// The last entry in the vec will become the top of the stack.
     PopNAndPush(usize, Vec<Box<dyn Gamemode>>),
+    /// Do another transition, and show it happening with an effect.
+    /// Nothing gets updated until the effect is done.
+    Animated {
+        inner: Box<Transition>,
+        effect: Effect,
+        /// How long it takes, in seconds
+        duration: f32,
+    },
 }
 
 impl Transition {
-    /// Apply the transition
-    pub fn apply(self, stack: &mut Vec<Box<dyn Gamemode>>, assets: &Assets) {
+    /// Show this transition with an effect.
+    pub fn animated(self, effect: Effect, duration: f32) -> Transition {
+        Transition::Animated {
+            inner: Box::new(self),
+            effect,
+            duration,
+        }
+    }
+
+    /// Apply the transition to the stack, right away.
+    pub fn apply(self, stack: &mut Vec<Box<dyn Gamemode>>, assets: &LogicAssets) {
         match self {
             Transition::None => {}
+            // The effect is `ModeStack`'s business
+            Transition::Animated { inner, .. } => inner.apply(stack, assets),
             Transition::Swap(new) => {
                 if !stack.is_empty() {
                     stack.pop();
@@ -72,7 +189,7 @@ impl Transition {
                 // this would be very bad otherwise
                 if stack.len() >= 2 {
                     stack.pop();
-                    stack.last_mut().unwrap().on_resume(&assets)
+                    stack.last_mut().unwrap().on_resume(assets)
                 }
             }
             Transition::PopNAndPush(count, mut news) => {
@@ -90,3 +207,179 @@ impl Transition {
         }
     }
 }
+
+/// The stack of gamemodes, along with any effect playing between two of them.
+pub struct ModeStack {
+    stack: Vec<Box<dyn Gamemode>>,
+    effect: Option<PlayingEffect>,
+    /// How many updates have run, for the debug overlay
+    updates: u64,
+}
+
+struct PlayingEffect {
+    effect: Effect,
+    /// How the mode on top looked right before the transition
+    from: Arc<Mutex<Box<dyn GamemodeDrawer>>>,
+    /// Seconds since it started
+    time: f32,
+    duration: f32,
+}
+
+impl ModeStack {
+    pub fn new(first: Box<dyn Gamemode>) -> Self {
+        Self {
+            stack: vec![first],
+            effect: None,
+            updates: 0,
+        }
+    }
+
+    /// Update the mode on top, and do whatever transition it asks for.
+    ///
+    /// While an effect plays nothing gets updated,
+    /// so clicks meant for the old mode don't land on the new one.
+    pub fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        frame_info: FrameInfo,
+        assets: &LogicAssets,
+    ) {
+        self.updates = frame_info.frames_ran;
+        if controls.clicked_down(Control::Debug) {
+            debug::toggle();
+        }
+
+        if let Some(effect) = &mut self.effect {
+            // Count update time instead of reading the clock so replays go the same way
+            effect.time += frame_info.dt;
+            if effect.time >= effect.duration {
+                self.effect = None;
+            }
+            return;
+        }
+
+        let transition = self
+            .stack
+            .last_mut()
+            .unwrap()
+            .update(controls, frame_info, assets);
+        self.apply(transition, assets);
+    }
+
+    fn apply(&mut self, transition: Transition, assets: &LogicAssets) {
+        if let Transition::Animated {
+            inner,
+            effect,
+            duration,
+        } = transition
+        {
+            let from = self.layers();
+            self.apply(*inner, assets);
+            // Fading doesn't move anything around, so it's fine for reduced motion
+            let effect = if Profile::current().settings.reduced_motion {
+                Effect::Fade
+            } else {
+                effect
+            };
+            self.effect = Some(PlayingEffect {
+                effect,
+                from: Arc::new(Mutex::new(from)),
+                time: 0.0,
+                duration,
+            });
+        } else {
+            transition.apply(&mut self.stack, assets);
+        }
+    }
+
+    /// Gather information about how to draw the mode on top, and the effect if there is one.
+    pub fn get_draw_info(&mut self) -> Box<dyn GamemodeDrawer> {
+        let to = self.layers();
+        let drawer: Box<dyn GamemodeDrawer> = match &self.effect {
+            Some(effect) => Box::new(EffectDrawer {
+                effect: effect.effect,
+                from: effect.from.clone(),
+                to,
+                time: effect.time,
+                duration: effect.duration,
+            }),
+            None => to,
+        };
+
+        if debug::shown() {
+            let top = self.stack.last().unwrap();
+            Box::new(DebugDrawer {
+                inner: drawer,
+                // Reach past the box, or it'd name the box
+                stack: self.stack.iter().map(|mode| (**mode).type_name()).collect(),
+                details: top.debug_info(),
+                updates: self.updates,
+            })
+        } else {
+            drawer
+        }
+    }
+
+    /// Drawers for the mode on top and every overlay's worth of modes showing through under it.
+    fn layers(&mut self) -> Box<dyn GamemodeDrawer> {
+        let bottom = self
+            .stack
+            .iter()
+            .rposition(|mode| !mode.is_overlay())
+            .unwrap_or(0);
+        let mut layers: Vec<_> = self.stack[bottom..]
+            .iter_mut()
+            .map(|mode| mode.get_draw_info())
+            .collect();
+        if layers.len() == 1 {
+            layers.pop().unwrap()
+        } else {
+            Box::new(LayeredDrawer { layers })
+        }
+    }
+}
+
+/// Draws modes over each other, dimming everything under each overlay.
+struct LayeredDrawer {
+    /// Bottom first
+    layers: Vec<Box<dyn GamemodeDrawer>>,
+}
+
+impl GamemodeDrawer for LayeredDrawer {
+    fn draw(&self, assets: &RenderAssets, frame_info: FrameInfo) {
+        for (idx, layer) in self.layers.iter().enumerate() {
+            if idx > 0 {
+                draw_rectangle(
+                    0.0,
+                    0.0,
+                    WIDTH,
+                    HEIGHT,
+                    Color::new(0.0, 0.0, 0.0, OVERLAY_DIM),
+                );
+            }
+            layer.draw(assets, frame_info);
+        }
+    }
+}
+
+// Only the headless driver needs to look inside
+#[cfg(test)]
+impl ModeStack {
+    /// Is an effect playing?
+    pub fn is_animating(&self) -> bool {
+        self.effect.is_some()
+    }
+
+    /// How many modes are on the stack.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    pub fn top(&self) -> &dyn Gamemode {
+        &**self.stack.last().unwrap()
+    }
+
+    pub fn top_mut(&mut self) -> &mut dyn Gamemode {
+        &mut **self.stack.last_mut().unwrap()
+    }
+}
@@ -0,0 +1,93 @@
+//! The developer overlay, toggled with `Control::Debug`.
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+use macroquad::prelude::*;
+use once_cell::sync::Lazy;
+
+use crate::{
+    assets::RenderAssets,
+    boilerplates::{FrameInfo, GamemodeDrawer},
+    utils::{
+        draw::hexcolor,
+        text::{self, Markup},
+    },
+    HEIGHT, WIDTH,
+};
+
+/// Is the overlay up?
+static SHOWN: AtomicBool = AtomicBool::new(false);
+
+/// Seconds between re-measuring the update rate, so the number holds still long enough to read
+const MEASURE_EVERY: f64 = 0.5;
+/// When the update rate was last measured, how many updates had happened then, and what it was
+static METER: Lazy<Mutex<Option<(f64, u64, f32)>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn shown() -> bool {
+    SHOWN.load(Ordering::Relaxed)
+}
+
+pub fn toggle() {
+    SHOWN.fetch_xor(true, Ordering::Relaxed);
+}
+
+/// Draws the overlay over everything else.
+pub(super) struct DebugDrawer {
+    pub inner: Box<dyn GamemodeDrawer>,
+    /// Bottom of the mode stack first
+    pub stack: Vec<&'static str>,
+    /// Whatever the mode on top wants to say
+    pub details: Vec<String>,
+    /// How many updates had run when this was made
+    pub updates: u64,
+}
+
+impl GamemodeDrawer for DebugDrawer {
+    fn draw(&self, assets: &RenderAssets, frame_info: FrameInfo) {
+        self.inner.draw(assets, frame_info);
+
+        let rate = {
+            let now = get_time();
+            let mut meter = METER.lock().unwrap();
+            match *meter {
+                Some((then, _, rate)) if now - then < MEASURE_EVERY => rate,
+                Some((then, updates, _)) => {
+                    let rate = self.updates.saturating_sub(updates) as f32 / (now - then) as f32;
+                    *meter = Some((now, self.updates, rate));
+                    rate
+                }
+                None => {
+                    *meter = Some((now, self.updates, 0.0));
+                    0.0
+                }
+            }
+        };
+
+        let mut lines = vec![
+            format!(
+                "frame {:.1}ms ({:.0} fps), {:.0} updates/s",
+                frame_info.dt * 1000.0,
+                frame_info.dt.recip(),
+                rate
+            ),
+            format!("modes: {}", self.stack.join(" > ")),
+        ];
+        lines.extend(self.details.iter().cloned());
+
+        let font = assets.textures.fonts.small;
+        let height = lines.len() as f32 * 10.0 + 4.0;
+        let top = HEIGHT - height;
+        draw_rectangle(0.0, top, WIDTH, height, Color::new(0.0, 0.0, 0.0, 0.75));
+        for (idx, line) in lines.iter().enumerate() {
+            text::draw_wrapped(
+                line,
+                vec2(2.0, top + 2.0 + idx as f32 * 10.0),
+                f32::INFINITY,
+                Markup::plain(font, hexcolor(0x5fcde4ff)),
+            );
+        }
+    }
+}
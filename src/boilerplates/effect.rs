@@ -0,0 +1,128 @@
+//! Effects shown while going from one gamemode to another.
+
+use std::sync::{Arc, Mutex};
+
+use macroquad::prelude::*;
+
+use crate::{
+    assets::RenderAssets,
+    boilerplates::{FrameInfo, GamemodeDrawer},
+    utils::draw::{canvas_camera, hexcolor},
+    HEIGHT, WIDTH,
+};
+
+/// How a transition looks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    /// Fade out to black, then fade the new mode in
+    Fade,
+    /// The new mode slides in over the old one from the left
+    Wipe,
+    /// The old mode breaks up into little squares that fall away
+    Dissolve,
+}
+
+/// How big the falling squares in a dissolve are
+const DISSOLVE_CELL: f32 = 16.0;
+/// How much of the progress each square takes to fall
+const DISSOLVE_FALL: f32 = 0.4;
+
+/// Draws an effect partway through.
+pub(super) struct EffectDrawer {
+    pub effect: Effect,
+    /// Shared so every frame of the effect can draw it; it's the same each time
+    pub from: Arc<Mutex<Box<dyn GamemodeDrawer>>>,
+    pub to: Box<dyn GamemodeDrawer>,
+    /// Seconds since it started, as of the last update
+    pub time: f32,
+    pub duration: f32,
+}
+
+impl GamemodeDrawer for EffectDrawer {
+    fn draw(&self, assets: &RenderAssets, frame_info: FrameInfo) {
+        let [from_canvas, to_canvas] = assets.effect_canvases;
+        let onto = |canvas: RenderTarget, drawer: &dyn GamemodeDrawer| {
+            push_camera_state();
+            set_camera(&canvas_camera(canvas));
+            clear_background(WHITE);
+            drawer.draw(assets, frame_info);
+            pop_camera_state();
+        };
+        onto(from_canvas, &**self.from.lock().unwrap());
+        onto(to_canvas, &*self.to);
+        let (from, to) = (from_canvas.texture, to_canvas.texture);
+
+        let t = ((self.time + frame_info.since_update()) / self.duration).min(1.0);
+        match self.effect {
+            Effect::Fade => {
+                // Black in the middle
+                let (shown, dark) = if t < 0.5 {
+                    (from, t * 2.0)
+                } else {
+                    (to, 2.0 - t * 2.0)
+                };
+                draw_texture(shown, 0.0, 0.0, WHITE);
+                draw_rectangle(0.0, 0.0, WIDTH, HEIGHT, Color::new(0.0, 0.0, 0.0, dark));
+            }
+            Effect::Wipe => {
+                draw_texture(from, 0.0, 0.0, WHITE);
+                let edge = (WIDTH * t).round();
+                draw_texture_ex(
+                    to,
+                    0.0,
+                    0.0,
+                    WHITE,
+                    DrawTextureParams {
+                        source: Some(Rect::new(0.0, 0.0, edge, HEIGHT)),
+                        ..Default::default()
+                    },
+                );
+                if t < 1.0 {
+                    draw_rectangle(edge, 0.0, 2.0, HEIGHT, hexcolor(0xfbf236ff));
+                }
+            }
+            Effect::Dissolve => {
+                draw_texture(to, 0.0, 0.0, WHITE);
+                let cols = (WIDTH / DISSOLVE_CELL).ceil() as u32;
+                let rows = (HEIGHT / DISSOLVE_CELL).ceil() as u32;
+                for y in 0..rows {
+                    for x in 0..cols {
+                        // Everything's done falling by the end
+                        let start = scatter(x, y) * (1.0 - DISSOLVE_FALL);
+                        let fall = ((t - start) / DISSOLVE_FALL).clamp(0.0, 1.0);
+                        if fall >= 1.0 {
+                            continue;
+                        }
+                        let corner = vec2(x as f32, y as f32) * DISSOLVE_CELL;
+                        let drop = fall * fall * HEIGHT / 2.0;
+                        draw_texture_ex(
+                            from,
+                            corner.x,
+                            corner.y + drop,
+                            Color::new(1.0, 1.0, 1.0, 1.0 - fall),
+                            DrawTextureParams {
+                                source: Some(Rect::new(
+                                    corner.x,
+                                    corner.y,
+                                    DISSOLVE_CELL,
+                                    DISSOLVE_CELL,
+                                )),
+                                ..Default::default()
+                            },
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Where between 0 and 1 a square lands, mixed up enough to look random
+/// but the same every frame.
+fn scatter(x: u32, y: u32) -> f32 {
+    let mut hash = x.wrapping_mul(0x9e37_79b9) ^ y.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 15;
+    hash = hash.wrapping_mul(0x2c1b_3c6d);
+    hash ^= hash >> 12;
+    (hash % 1024) as f32 / 1024.0
+}
@@ -0,0 +1,88 @@
+//! Running gamemodes without a window or a GPU, for testing.
+//!
+//! Nothing gets drawn; inputs are fed in by hand and the modes are poked at directly.
+//! Transition effects still play and still hold up input, so wait them out with `settle`.
+
+use crate::{
+    assets::LogicAssets,
+    boilerplates::{FrameInfo, Gamemode, ModeStack},
+    controls::InputSubscriber,
+};
+
+/// Runs a stack of gamemodes the same way the real game loop does, minus the drawing.
+pub struct Headless {
+    pub controls: InputSubscriber,
+    pub assets: LogicAssets,
+    stack: ModeStack,
+    frame_info: FrameInfo,
+}
+
+impl Headless {
+    /// Load the assets off the disk and start with the mode `first` makes out of them.
+    pub fn new(first: impl FnOnce(&LogicAssets) -> Box<dyn Gamemode>) -> Self {
+        Self::with_assets(LogicAssets::init_blocking().unwrap(), first)
+    }
+
+    /// Start with assets that are already loaded.
+    pub fn with_assets(
+        assets: LogicAssets,
+        first: impl FnOnce(&LogicAssets) -> Box<dyn Gamemode>,
+    ) -> Self {
+        let stack = ModeStack::new(first(&assets));
+        Self {
+            controls: InputSubscriber::headless(InputSubscriber::default_controls()),
+            assets,
+            stack,
+            frame_info: FrameInfo::first_update(),
+        }
+    }
+
+    /// Run one update, feeding in whatever inputs were queued up on `controls`.
+    pub fn update(&mut self) {
+        self.controls.update();
+        self.stack
+            .update(&self.controls, self.frame_info, &self.assets);
+        self.frame_info.frames_ran += 1;
+
+        // Nothing's going to play them
+        self.assets.sounds.take();
+    }
+
+    /// Run a bunch of updates.
+    pub fn run(&mut self, updates: usize) {
+        for _ in 0..updates {
+            self.update();
+        }
+    }
+
+    /// Run updates until any transition effect is done playing.
+    pub fn settle(&mut self) {
+        while self.stack.is_animating() {
+            self.update();
+        }
+    }
+
+    /// How many modes are on the stack.
+    pub fn depth(&self) -> usize {
+        self.stack.depth()
+    }
+
+    /// The mode on top of the stack, if it's a `T`.
+    pub fn top<T: Gamemode + 'static>(&self) -> Option<&T> {
+        self.stack.top().as_any().downcast_ref()
+    }
+
+    pub fn top_mut<T: Gamemode + 'static>(&mut self) -> Option<&mut T> {
+        self.stack.top_mut().as_any_mut().downcast_mut()
+    }
+
+    /// The mode on top of the stack, when it had better be a `T`.
+    pub fn expect_top<T: Gamemode + 'static>(&self) -> &T {
+        self.top().unwrap_or_else(|| {
+            panic!(
+                "expected a {} on top of the stack",
+                std::any::type_name::<T>()
+            )
+        })
+    }
+}
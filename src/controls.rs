@@ -1,66 +1,760 @@
 use ahash::AHashMap;
-use cogs_gamedev::controls::EventInputHandler;
-use enum_map::Enum;
+#[cfg(feature = "gamepad")]
+use ahash::AHashSet;
+use anyhow::anyhow;
+use cogs_gamedev::controls::{EventInputHandler, InputHandler};
+use enum_map::{Enum, EnumMap};
 use macroquad::{
-    miniquad::{self, Context, KeyMods},
+    input::simulate_mouse_with_touch,
+    miniquad::{self, Context, KeyMods, TouchPhase},
     prelude::{
+        info,
         utils::{register_input_subscriber, repeat_all_miniquad_input},
-        KeyCode, MouseButton,
+        warn, KeyCode, MouseButton,
     },
 };
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize, Serializer};
 
-use std::collections::HashMap;
+use std::{cell::RefCell, fmt, path::Path, str::FromStr};
+
+use crate::utils::{
+    draw::{mouse_position_pixel, screen_to_pixel},
+    profile::Profile,
+};
+
+mod record;
+pub use record::InputRecording;
+use record::{InputRecorder, Replayer};
+mod touch;
+use touch::TouchTracker;
 
 /// The controls
-#[derive(Enum, Copy, Clone)]
+///
+/// This (de)serializes as its name, so adding controls doesn't scramble saved bindings.
+#[derive(Enum, Copy, Clone, Debug, PartialEq, Eq)]
 pub enum Control {
     Click,
     RightClick,
+    /// Hold this and move to pan the board
+    Pan,
+    ZoomIn,
+    ZoomOut,
+    /// Put down what you're holding, or back out of a tool
+    Cancel,
+    /// Toggle the explain-the-error overlay
+    Explain,
+    /// Toggle the glue tool
+    Glue,
+    /// Toggle the cut tool
+    Cut,
+    Undo,
+    /// Put the level back how it started
+    Restart,
+
+    /// Pick up or put down at the keyboard cursor
+    Select,
+    /// Jump the keyboard cursor to the next fragment
+    NextFragment,
+    Up,
+    Down,
+    Left,
+    Right,
+
+    /// Open the settings screen
+    OpenSettings,
+    /// Toggle the debug overlay
     Debug,
+    /// Save the board as a level file, while the debug overlay is up
+    DumpBoard,
+}
+
+impl Control {
+    /// Every control, in the order they're shown on the controls screen.
+    pub const ALL: &'static [Control] = &[
+        Control::Click,
+        Control::RightClick,
+        Control::Pan,
+        Control::ZoomIn,
+        Control::ZoomOut,
+        Control::Cancel,
+        Control::Explain,
+        Control::Glue,
+        Control::Cut,
+        Control::Undo,
+        Control::Restart,
+        Control::Select,
+        Control::NextFragment,
+        Control::Up,
+        Control::Down,
+        Control::Left,
+        Control::Right,
+        Control::OpenSettings,
+        Control::Debug,
+        Control::DumpBoard,
+    ];
+
+    /// Name to show the player.
+    pub fn name(self) -> &'static str {
+        match self {
+            Control::Click => "click",
+            Control::RightClick => "right click",
+            Control::Pan => "pan",
+            Control::ZoomIn => "zoom in",
+            Control::ZoomOut => "zoom out",
+            Control::Cancel => "cancel",
+            Control::Explain => "explain",
+            Control::Glue => "glue",
+            Control::Cut => "cut",
+            Control::Undo => "undo",
+            Control::Restart => "restart level",
+            Control::Select => "select",
+            Control::NextFragment => "next fragment",
+            Control::Up => "up",
+            Control::Down => "down",
+            Control::Left => "left",
+            Control::Right => "right",
+            Control::OpenSettings => "settings",
+            Control::Debug => "debug",
+            Control::DumpBoard => "dump board",
+        }
+    }
+}
+
+impl FromStr for Control {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Control::ALL
+            .iter()
+            .find(|control| format!("{:?}", control) == s)
+            .copied()
+            .ok_or_else(|| anyhow!("unknown control `{}`", s))
+    }
+}
+
+impl Serialize for Control {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:?}", self))
+    }
+}
+
+impl<'de> Deserialize<'de> for Control {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(D::Error::custom)
+    }
 }
 
-/// Combo keycode and mouse button code
-#[derive(Hash, PartialEq, Eq, Copy, Clone)]
+/// Combo keycode, mouse button, scroll wheel, gamepad button and touch gesture code
+///
+/// This (de)serializes as its name, so saved bindings don't depend on miniquad's enum layout.
+#[derive(Hash, PartialEq, Eq, Copy, Clone, Debug)]
 pub enum InputCode {
     Key(KeyCode),
     Mouse(MouseButton),
+    Wheel(Wheel),
+    Gamepad(GamepadButton),
+    Touch(TouchGesture),
+}
+
+/// Which way the scroll wheel turned.
+///
+/// Each click of the wheel is a press, released on the next update.
+#[derive(Hash, PartialEq, Eq, Copy, Clone, Debug)]
+pub enum Wheel {
+    Up,
+    Down,
+}
+
+/// Buttons on a gamepad.
+///
+/// The left stick pushed in each direction counts as a button too,
+/// so it can be bound like the d-pad.
+#[derive(Hash, PartialEq, Eq, Copy, Clone, Debug)]
+pub enum GamepadButton {
+    South,
+    East,
+    North,
+    West,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+    LeftShoulder,
+    RightShoulder,
+    Start,
+    Select,
+    StickUp,
+    StickDown,
+    StickLeft,
+    StickRight,
+}
+
+/// Things you can do on a touchscreen.
+///
+/// See the `touch` module for how fingers turn into these.
+#[derive(Hash, PartialEq, Eq, Copy, Clone, Debug)]
+pub enum TouchGesture {
+    Tap,
+    Hold,
+    TwoFinger,
+}
+
+macro_rules! key_names {
+    ($($key:ident),* $(,)?) => {
+        /// Every key that can be bound, with its name.
+        const KEY_NAMES: &[(KeyCode, &str)] = &[$((KeyCode::$key, stringify!($key))),*];
+    };
+}
+
+key_names! {
+    Space, Apostrophe, Comma, Minus, Period, Slash,
+    Key0, Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9,
+    Semicolon, Equal,
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    LeftBracket, Backslash, RightBracket, GraveAccent,
+    Escape, Enter, Tab, Backspace, Insert, Delete,
+    Right, Left, Down, Up, PageUp, PageDown, Home, End,
+    CapsLock, ScrollLock, NumLock, PrintScreen, Pause,
+    F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Kp0, Kp1, Kp2, Kp3, Kp4, Kp5, Kp6, Kp7, Kp8, Kp9,
+    KpDecimal, KpDivide, KpMultiply, KpSubtract, KpAdd, KpEnter, KpEqual,
+    LeftShift, LeftControl, LeftAlt, LeftSuper,
+    RightShift, RightControl, RightAlt, RightSuper, Menu,
+}
+
+const MOUSE_NAMES: &[(MouseButton, &str)] = &[
+    (MouseButton::Left, "Mouse Left"),
+    (MouseButton::Right, "Mouse Right"),
+    (MouseButton::Middle, "Mouse Middle"),
+];
+
+const WHEEL_NAMES: &[(Wheel, &str)] = &[(Wheel::Up, "Wheel Up"), (Wheel::Down, "Wheel Down")];
+
+const TOUCH_NAMES: &[(TouchGesture, &str)] = &[
+    (TouchGesture::Tap, "Touch Tap"),
+    (TouchGesture::Hold, "Touch Hold"),
+    (TouchGesture::TwoFinger, "Two Finger Drag"),
+];
+
+const GAMEPAD_NAMES: &[(GamepadButton, &str)] = &[
+    (GamepadButton::South, "Pad South"),
+    (GamepadButton::East, "Pad East"),
+    (GamepadButton::North, "Pad North"),
+    (GamepadButton::West, "Pad West"),
+    (GamepadButton::DPadUp, "D-Pad Up"),
+    (GamepadButton::DPadDown, "D-Pad Down"),
+    (GamepadButton::DPadLeft, "D-Pad Left"),
+    (GamepadButton::DPadRight, "D-Pad Right"),
+    (GamepadButton::LeftShoulder, "Pad Left Shoulder"),
+    (GamepadButton::RightShoulder, "Pad Right Shoulder"),
+    (GamepadButton::Start, "Pad Start"),
+    (GamepadButton::Select, "Pad Select"),
+    (GamepadButton::StickUp, "Stick Up"),
+    (GamepadButton::StickDown, "Stick Down"),
+    (GamepadButton::StickLeft, "Stick Left"),
+    (GamepadButton::StickRight, "Stick Right"),
+];
+
+/// How far the stick has to be pushed to press a stick direction.
+#[cfg(feature = "gamepad")]
+const STICK_PRESS: f32 = 0.6;
+/// How far back the stick has to come to release it again.
+#[cfg(feature = "gamepad")]
+const STICK_RELEASE: f32 = 0.3;
+
+impl fmt::Display for InputCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            InputCode::Key(key) => KEY_NAMES
+                .iter()
+                .find_map(|(k, name)| (k == key).then_some(*name)),
+            InputCode::Mouse(button) => MOUSE_NAMES
+                .iter()
+                .find_map(|(b, name)| (b == button).then_some(*name)),
+            InputCode::Gamepad(button) => GAMEPAD_NAMES
+                .iter()
+                .find_map(|(b, name)| (b == button).then_some(*name)),
+            InputCode::Wheel(wheel) => WHEEL_NAMES
+                .iter()
+                .find_map(|(w, name)| (w == wheel).then_some(*name)),
+            InputCode::Touch(gesture) => TOUCH_NAMES
+                .iter()
+                .find_map(|(g, name)| (g == gesture).then_some(*name)),
+        };
+        match name {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "{:?}", self),
+        }
+    }
+}
+
+impl FromStr for InputCode {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        KEY_NAMES
+            .iter()
+            .find_map(|(key, name)| (*name == s).then_some(InputCode::Key(*key)))
+            .or_else(|| {
+                MOUSE_NAMES
+                    .iter()
+                    .find_map(|(button, name)| (*name == s).then_some(InputCode::Mouse(*button)))
+            })
+            .or_else(|| {
+                GAMEPAD_NAMES
+                    .iter()
+                    .find_map(|(button, name)| (*name == s).then_some(InputCode::Gamepad(*button)))
+            })
+            .or_else(|| {
+                WHEEL_NAMES
+                    .iter()
+                    .find_map(|(wheel, name)| (*name == s).then_some(InputCode::Wheel(*wheel)))
+            })
+            .or_else(|| {
+                TOUCH_NAMES
+                    .iter()
+                    .find_map(|(gesture, name)| (*name == s).then_some(InputCode::Touch(*gesture)))
+            })
+            .ok_or_else(|| anyhow!("unknown input `{}`", s))
+    }
+}
+
+impl Serialize for InputCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for InputCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(D::Error::custom)
+    }
 }
 
 /// Event handler to hook into miniquad and get inputs
-#[derive(Clone)]
 pub struct InputSubscriber {
     controls: EventInputHandler<InputCode, Control>,
-    subscriber_id: usize,
+    /// `None` if this isn't hooked up to miniquad and only gets inputs by hand.
+    subscriber_id: Option<usize>,
+
+    /// The bindings currently in use.
+    bindings: AHashMap<InputCode, Control>,
+    /// New bindings to swap to on the next update.
+    ///
+    /// Modes only get a `&InputSubscriber`, so this is how they change the bindings.
+    rebind: RefCell<Option<AHashMap<InputCode, Control>>>,
+    /// The raw input pressed this update, if any, for capturing new bindings.
+    just_pressed: Option<InputCode>,
+    /// Which controls were held down last update, to notice them being let go.
+    was_pressed: EnumMap<Control, bool>,
+    /// Text typed this update, for naming things.
+    /// This isn't recorded, so replays don't type anything.
+    typed: String,
+    /// Text typed since the last update.
+    typing: String,
+
+    /// Presses (`true`) and releases (`false`) to apply on the next update.
+    queue: Vec<(InputCode, bool)>,
+    /// Which stick directions are currently held down.
+    #[cfg(feature = "gamepad")]
+    stick_held: AHashSet<GamepadButton>,
+    touches: TouchTracker,
+    /// Wheel clicks to let go of on the next update
+    wheel_release: Vec<InputCode>,
+
+    /// Where the mouse is in canvas pixels, as of the last update.
+    /// Touching the screen moves this too.
+    mouse: (f32, f32),
+    /// Where the real mouse was last update, so touches aren't overwritten by a mouse that didn't move.
+    real_mouse: (f32, f32),
+    /// How many updates since recording or replaying started.
+    frame: u64,
+    recorder: Option<InputRecorder>,
+    replayer: Option<Replayer>,
 }
 
 impl InputSubscriber {
     pub fn new() -> Self {
         // the science kid
         let sid = register_input_subscriber();
+        // We do our own thing with touches, thanks
+        simulate_mouse_with_touch(false);
+
+        // Start with the player's own bindings if they've saved any
+        let bindings = Self::with_overrides(&Profile::current().controls);
 
         InputSubscriber {
-            controls: EventInputHandler::new(Self::default_controls()),
-            subscriber_id: sid,
+            subscriber_id: Some(sid),
+            ..Self::headless(bindings)
         }
     }
 
+    /// Make an input subscriber that isn't hooked up to miniquad.
+    /// It only gets inputs you feed it by hand.
+    pub fn headless(bindings: AHashMap<InputCode, Control>) -> Self {
+        InputSubscriber {
+            controls: EventInputHandler::new(bindings.clone()),
+            subscriber_id: None,
+            bindings,
+            rebind: RefCell::new(None),
+            just_pressed: None,
+            was_pressed: EnumMap::default(),
+            typed: String::new(),
+            typing: String::new(),
+            queue: Vec::new(),
+            #[cfg(feature = "gamepad")]
+            stick_held: AHashSet::new(),
+            touches: TouchTracker::default(),
+            wheel_release: Vec::new(),
+            mouse: (0.0, 0.0),
+            real_mouse: (0.0, 0.0),
+            frame: 0,
+            recorder: None,
+            replayer: None,
+        }
+    }
+
+    /// The bindings currently in use.
+    pub fn bindings(&self) -> &AHashMap<InputCode, Control> {
+        &self.bindings
+    }
+
+    /// Swap to these bindings, starting on the next update.
+    pub fn rebind(&self, bindings: AHashMap<InputCode, Control>) {
+        *self.rebind.borrow_mut() = Some(bindings);
+    }
+
+    /// Was this control let go of this update?
+    pub fn clicked_up(&self, control: Control) -> bool {
+        self.was_pressed[control] && self.released(control)
+    }
+
+    /// The raw input that was pressed this update, whatever it's bound to.
+    pub fn just_pressed(&self) -> Option<InputCode> {
+        self.just_pressed
+    }
+
+    /// Printable text typed this update.
+    pub fn typed(&self) -> &str {
+        &self.typed
+    }
+
+    /// Where the mouse is in canvas pixels.
+    ///
+    /// Use this instead of asking macroquad so recordings play back the same.
+    pub fn mouse_pixel(&self) -> (f32, f32) {
+        self.mouse
+    }
+
+    /// Move the mouse of a headless subscriber. It takes effect right away.
+    #[cfg(test)]
+    pub fn set_mouse(&mut self, mouse: (f32, f32)) {
+        self.mouse = mouse;
+    }
+
+    /// Start writing every input to this file.
+    pub fn record_to(&mut self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        self.recorder = Some(InputRecorder::create(path, &self.bindings)?);
+        self.frame = 0;
+        Ok(())
+    }
+
+    /// Ignore the real inputs and play back this recording instead.
+    ///
+    /// Once it runs out the player gets control back, with their own bindings.
+    pub fn start_replay(&mut self, recording: InputRecording) {
+        let player_bindings = std::mem::replace(&mut self.bindings, recording.bindings.clone());
+        self.controls = EventInputHandler::new(recording.bindings.clone());
+        self.replayer = Some(Replayer::new(recording, player_bindings));
+        self.frame = 0;
+    }
+
+    /// Is there a recording still playing?
+    #[cfg(test)]
+    pub fn is_replaying(&self) -> bool {
+        self.replayer.is_some()
+    }
+
     pub fn default_controls() -> AHashMap<InputCode, Control> {
         let mut controls = AHashMap::new();
 
         controls.insert(InputCode::Mouse(MouseButton::Left), Control::Click);
         controls.insert(InputCode::Mouse(MouseButton::Right), Control::RightClick);
-        // Also let middle-click pan
-        controls.insert(InputCode::Mouse(MouseButton::Middle), Control::RightClick);
+        // Right-dragging pans too, but middle-click only pans
+        controls.insert(InputCode::Mouse(MouseButton::Middle), Control::Pan);
+
+        controls.insert(InputCode::Key(KeyCode::Escape), Control::Cancel);
+        controls.insert(InputCode::Key(KeyCode::E), Control::Explain);
+        controls.insert(InputCode::Key(KeyCode::G), Control::Glue);
+        controls.insert(InputCode::Key(KeyCode::C), Control::Cut);
+        controls.insert(InputCode::Key(KeyCode::Z), Control::Undo);
+        controls.insert(InputCode::Key(KeyCode::R), Control::Restart);
+
+        controls.insert(InputCode::Key(KeyCode::Space), Control::Select);
+        controls.insert(InputCode::Key(KeyCode::Enter), Control::Select);
+        controls.insert(InputCode::Key(KeyCode::Tab), Control::NextFragment);
+        controls.insert(InputCode::Key(KeyCode::Up), Control::Up);
+        controls.insert(InputCode::Key(KeyCode::Down), Control::Down);
+        controls.insert(InputCode::Key(KeyCode::Left), Control::Left);
+        controls.insert(InputCode::Key(KeyCode::Right), Control::Right);
 
+        controls.insert(InputCode::Wheel(Wheel::Up), Control::ZoomIn);
+        controls.insert(InputCode::Wheel(Wheel::Down), Control::ZoomOut);
+        controls.insert(InputCode::Key(KeyCode::Equal), Control::ZoomIn);
+        controls.insert(InputCode::Key(KeyCode::Minus), Control::ZoomOut);
+
+        controls.insert(InputCode::Key(KeyCode::F1), Control::OpenSettings);
         controls.insert(InputCode::Key(KeyCode::Backslash), Control::Debug);
+        controls.insert(InputCode::Key(KeyCode::F2), Control::DumpBoard);
+
+        for (button, control) in [
+            (GamepadButton::DPadUp, Control::Up),
+            (GamepadButton::DPadDown, Control::Down),
+            (GamepadButton::DPadLeft, Control::Left),
+            (GamepadButton::DPadRight, Control::Right),
+            (GamepadButton::StickUp, Control::Up),
+            (GamepadButton::StickDown, Control::Down),
+            (GamepadButton::StickLeft, Control::Left),
+            (GamepadButton::StickRight, Control::Right),
+            (GamepadButton::South, Control::Select),
+            (GamepadButton::East, Control::Cancel),
+            (GamepadButton::North, Control::NextFragment),
+            (GamepadButton::West, Control::Undo),
+            (GamepadButton::LeftShoulder, Control::Glue),
+            (GamepadButton::RightShoulder, Control::Cut),
+            (GamepadButton::Select, Control::Explain),
+            (GamepadButton::Start, Control::OpenSettings),
+        ] {
+            controls.insert(InputCode::Gamepad(button), control);
+        }
+
+        controls.insert(InputCode::Touch(TouchGesture::Tap), Control::Click);
+        controls.insert(InputCode::Touch(TouchGesture::Hold), Control::RightClick);
+        controls.insert(InputCode::Touch(TouchGesture::TwoFinger), Control::Pan);
 
         controls
     }
 
+    /// The default bindings with the player's changes on top.
+    ///
+    /// Only the changes get saved, so controls added since then still get their default inputs.
+    pub fn with_overrides(
+        overrides: &AHashMap<InputCode, Option<Control>>,
+    ) -> AHashMap<InputCode, Control> {
+        let mut bindings = Self::default_controls();
+        for (input, control) in overrides {
+            match control {
+                Some(control) => bindings.insert(*input, *control),
+                None => bindings.remove(input),
+            };
+        }
+        bindings
+    }
+
+    /// What's different about these bindings from the defaults, for saving.
+    pub fn overrides(
+        bindings: &AHashMap<InputCode, Control>,
+    ) -> AHashMap<InputCode, Option<Control>> {
+        let defaults = Self::default_controls();
+        let changed = bindings
+            .iter()
+            .filter(|(input, control)| defaults.get(input) != Some(control))
+            .map(|(input, control)| (*input, Some(*control)));
+        let removed = defaults
+            .keys()
+            .filter(|input| !bindings.contains_key(input))
+            .map(|input| (*input, None));
+        changed.chain(removed).collect()
+    }
+
     pub fn update(&mut self) {
-        repeat_all_miniquad_input(self, self.subscriber_id);
+        if let Some(bindings) = self.rebind.get_mut().take() {
+            self.controls = EventInputHandler::new(bindings.clone());
+            self.bindings = bindings;
+        }
+
+        self.just_pressed = None;
+        if let Some(sid) = self.subscriber_id {
+            let real_mouse = mouse_position_pixel();
+            if real_mouse != self.real_mouse && self.replayer.is_none() {
+                self.mouse = real_mouse;
+            }
+            self.real_mouse = real_mouse;
+            repeat_all_miniquad_input(self, sid);
+        }
+
+        // Wait an update after the last frame so its inputs still get seen
+        if self.replayer.as_ref().is_some_and(Replayer::finished) {
+            let replayer = self.replayer.take().unwrap();
+            info!("Replay finished on update {}", self.frame);
+            self.controls = EventInputHandler::new(replayer.player_bindings.clone());
+            self.bindings = replayer.player_bindings;
+        }
+
+        self.typed = std::mem::take(&mut self.typing);
+        if let Some(replayer) = &mut self.replayer {
+            // The recording stands in for everything real
+            self.queue.clear();
+            self.typed.clear();
+            if let Some(frame) = replayer.frame(self.frame) {
+                self.mouse = frame.mouse;
+                self.queue.extend_from_slice(&frame.events);
+            }
+        }
+
+        let events = std::mem::take(&mut self.queue);
+        for &(input, down) in &events {
+            if down {
+                self.controls.input_down(input);
+                self.just_pressed = Some(input);
+            } else {
+                self.controls.input_up(input);
+            }
+        }
+        self.touches.tick(&mut self.queue);
+        for wheel in self.wheel_release.drain(..) {
+            self.queue.push((wheel, false));
+        }
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(oh_no) = recorder.write_frame(self.frame, self.mouse, &events) {
+                warn!("Couldn't write the input recording, stopping!\n{:?}", oh_no);
+                self.recorder = None;
+            }
+        }
+
+        self.frame += 1;
+        for (control, was) in self.was_pressed.iter_mut() {
+            *was = self.controls.pressed(control);
+        }
         self.controls.update();
     }
+
+    /// Press an input by hand. It takes effect on the next update.
+    pub fn press(&mut self, input: InputCode) {
+        self.queue.push((input, true));
+    }
+
+    /// Release an input by hand. It takes effect on the next update.
+    pub fn release(&mut self, input: InputCode) {
+        self.queue.push((input, false));
+    }
+
+    /// Feed in a finger touching the screen, in canvas pixels. It takes effect on the next update.
+    pub fn touch(&mut self, phase: TouchPhase, id: u64, pos: (f32, f32)) {
+        self.touches
+            .event(phase, id, pos, &mut self.queue, &mut self.mouse);
+    }
+
+    /// Press a gamepad button. It takes effect on the next update.
+    #[cfg(feature = "gamepad")]
+    pub fn gamepad_down(&mut self, button: GamepadButton) {
+        self.press(InputCode::Gamepad(button));
+    }
+
+    /// Release a gamepad button. It takes effect on the next update.
+    #[cfg(feature = "gamepad")]
+    pub fn gamepad_up(&mut self, button: GamepadButton) {
+        self.release(InputCode::Gamepad(button));
+    }
+
+    /// Tell the subscriber where the left stick is, with up and right positive.
+    ///
+    /// Pushing it far enough in a direction presses that stick direction.
+    #[cfg(feature = "gamepad")]
+    pub fn left_stick(&mut self, x: f32, y: f32) {
+        for (amount, button) in [
+            (y, GamepadButton::StickUp),
+            (-y, GamepadButton::StickDown),
+            (-x, GamepadButton::StickLeft),
+            (x, GamepadButton::StickRight),
+        ] {
+            if amount > STICK_PRESS && self.stick_held.insert(button) {
+                self.gamepad_down(button);
+            } else if amount < STICK_RELEASE && self.stick_held.remove(&button) {
+                self.gamepad_up(button);
+            }
+        }
+    }
+}
+
+/// Reads gamepads with `gilrs` and feeds them to an `InputSubscriber`.
+///
+/// This isn't part of the subscriber because `gilrs` can't always be sent between threads;
+/// make it on whichever thread does the updating.
+#[cfg(feature = "gamepad")]
+pub struct GamepadPoller {
+    gilrs: Option<gilrs::Gilrs>,
+    stick: (f32, f32),
+}
+
+#[cfg(feature = "gamepad")]
+impl GamepadPoller {
+    // shut up clippy
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        let gilrs = match gilrs::Gilrs::new() {
+            Ok(it) => Some(it),
+            Err(oh_no) => {
+                macroquad::prelude::warn!("Couldn't start gamepad support!\n{:?}", oh_no);
+                None
+            }
+        };
+        Self {
+            gilrs,
+            stick: (0.0, 0.0),
+        }
+    }
+
+    /// Feed everything that's happened since the last poll to the subscriber.
+    /// Call this right before `InputSubscriber::update`.
+    pub fn poll(&mut self, controls: &mut InputSubscriber) {
+        use gilrs::{Axis, Button, EventType};
+
+        let gilrs = match &mut self.gilrs {
+            Some(it) => it,
+            None => return,
+        };
+        while let Some(event) = gilrs.next_event() {
+            let button = |button: Button| {
+                Some(match button {
+                    Button::South => GamepadButton::South,
+                    Button::East => GamepadButton::East,
+                    Button::North => GamepadButton::North,
+                    Button::West => GamepadButton::West,
+                    Button::DPadUp => GamepadButton::DPadUp,
+                    Button::DPadDown => GamepadButton::DPadDown,
+                    Button::DPadLeft => GamepadButton::DPadLeft,
+                    Button::DPadRight => GamepadButton::DPadRight,
+                    Button::LeftTrigger => GamepadButton::LeftShoulder,
+                    Button::RightTrigger => GamepadButton::RightShoulder,
+                    Button::Start => GamepadButton::Start,
+                    Button::Select => GamepadButton::Select,
+                    _ => return None,
+                })
+            };
+            match event.event {
+                EventType::ButtonPressed(pressed, _) => {
+                    if let Some(pressed) = button(pressed) {
+                        controls.gamepad_down(pressed);
+                    }
+                }
+                EventType::ButtonReleased(released, _) => {
+                    if let Some(released) = button(released) {
+                        controls.gamepad_up(released);
+                    }
+                }
+                EventType::AxisChanged(Axis::LeftStickX, x, _) => {
+                    self.stick.0 = x;
+                    controls.left_stick(self.stick.0, self.stick.1);
+                }
+                EventType::AxisChanged(Axis::LeftStickY, y, _) => {
+                    self.stick.1 = y;
+                    controls.left_stick(self.stick.0, self.stick.1);
+                }
+                _ => {}
+            }
+        }
+    }
 }
 
 impl std::ops::Deref for InputSubscriber {
@@ -84,12 +778,25 @@ impl miniquad::EventHandler for InputSubscriber {
         repeat: bool,
     ) {
         if !repeat {
-            self.controls.input_down(InputCode::Key(keycode));
+            self.press(InputCode::Key(keycode));
         }
     }
 
     fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymods: KeyMods) {
-        self.controls.input_up(InputCode::Key(keycode));
+        self.release(InputCode::Key(keycode));
+    }
+
+    fn char_event(
+        &mut self,
+        _ctx: &mut Context,
+        character: char,
+        _keymods: KeyMods,
+        _repeat: bool,
+    ) {
+        // Backspace and friends come through as keys
+        if !character.is_control() {
+            self.typing.push(character);
+        }
     }
 
     fn mouse_button_down_event(
@@ -99,9 +806,27 @@ impl miniquad::EventHandler for InputSubscriber {
         _x: f32,
         _y: f32,
     ) {
-        self.controls.input_down(InputCode::Mouse(button));
+        self.press(InputCode::Mouse(button));
     }
     fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: MouseButton, _x: f32, _y: f32) {
-        self.controls.input_up(InputCode::Mouse(button));
+        self.release(InputCode::Mouse(button));
+    }
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) {
+        let wheel = if y > 0.0 {
+            Wheel::Up
+        } else if y < 0.0 {
+            Wheel::Down
+        } else {
+            return;
+        };
+        self.press(InputCode::Wheel(wheel));
+        self.wheel_release.push(InputCode::Wheel(wheel));
+    }
+
+    fn touch_event(&mut self, _ctx: &mut Context, phase: TouchPhase, id: u64, x: f32, y: f32) {
+        self.touch(phase, id, screen_to_pixel(x, y));
     }
 }
+
+mod test;
@@ -0,0 +1,211 @@
+//! Writing every input to a file and playing it back later.
+//!
+//! The file is plain text so it can be pasted into a bug report:
+//!
+//! ```text
+//! # wgj_210 input recording
+//! bind\tMouse Left\tClick
+//! frame\t12\t101.5\t40.25\t+Mouse Left
+//! frame\t15\t101.5\t40.25\t-Mouse Left
+//! ```
+//!
+//! Fields are separated by tabs (the `\t`s up there) because input names have spaces in them.
+//! `frame` lines are only written on updates where something changed.
+
+use ahash::AHashMap;
+use anyhow::{anyhow, bail, Context};
+
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::Path,
+};
+
+use super::{Control, InputCode};
+
+const HEADER: &str = "# wgj_210 input recording";
+
+/// What happened on one update.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputFrame {
+    /// Which update this happened on, counting from the start of the recording.
+    pub frame: u64,
+    /// Mouse position in canvas pixels.
+    pub mouse: (f32, f32),
+    /// Presses (`true`) and releases (`false`), in the order they happened.
+    pub events: Vec<(InputCode, bool)>,
+}
+
+/// A whole recorded session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputRecording {
+    /// The bindings in use when the recording started.
+    pub bindings: AHashMap<InputCode, Control>,
+    /// Every frame something changed on, in order.
+    pub frames: Vec<InputFrame>,
+}
+
+impl InputRecording {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("couldn't read recording {}", path.display()))?;
+        Self::parse(&text).with_context(|| format!("couldn't parse recording {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut lines = text.lines().enumerate();
+        match lines.next() {
+            Some((_, HEADER)) => {}
+            _ => bail!("missing the `{}` header", HEADER),
+        }
+
+        let mut recording = Self {
+            bindings: AHashMap::new(),
+            frames: Vec::new(),
+        };
+        for (idx, line) in lines {
+            recording
+                .parse_line(line)
+                .with_context(|| format!("on line {}", idx + 1))?;
+        }
+        Ok(recording)
+    }
+
+    fn parse_line(&mut self, line: &str) -> anyhow::Result<()> {
+        let mut fields = line.split('\t');
+        match fields.next() {
+            Some("bind") => {
+                let (input, control) = match (fields.next(), fields.next()) {
+                    (Some(input), Some(control)) => (input, control),
+                    _ => bail!("wanted `bind <input> <control>`"),
+                };
+                self.bindings.insert(input.parse()?, control.parse()?);
+            }
+            Some("frame") => {
+                let mut number = || {
+                    fields
+                        .next()
+                        .ok_or_else(|| anyhow!("wanted `frame <n> <x> <y> <events...>`"))
+                };
+                let frame: u64 = number()?.parse()?;
+                let mouse: (f32, f32) = (number()?.parse()?, number()?.parse()?);
+                if let Some(last) = self.frames.last() {
+                    if last.frame >= frame {
+                        bail!("frame {} is out of order", frame);
+                    }
+                }
+
+                let events = fields
+                    .map(|event| {
+                        let down = match event.chars().next() {
+                            Some('+') => true,
+                            Some('-') => false,
+                            _ => bail!("event `{}` needs to start with + or -", event),
+                        };
+                        Ok((event[1..].parse()?, down))
+                    })
+                    .collect::<anyhow::Result<Vec<(InputCode, bool)>>>()?;
+                self.frames.push(InputFrame {
+                    frame,
+                    mouse,
+                    events,
+                });
+            }
+            Some(comment) if comment.is_empty() || comment.starts_with('#') => {}
+            Some(other) => bail!("unknown line kind `{}`", other),
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+/// Writes inputs to a file as they happen.
+pub(super) struct InputRecorder {
+    out: File,
+    /// The mouse position last written, so we only write when it moves.
+    last_mouse: Option<(f32, f32)>,
+}
+
+impl InputRecorder {
+    pub fn create(
+        path: impl AsRef<Path>,
+        bindings: &AHashMap<InputCode, Control>,
+    ) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let mut out = File::create(path)
+            .with_context(|| format!("couldn't create recording {}", path.display()))?;
+
+        writeln!(out, "{}", HEADER)?;
+        // Sort them so the same bindings always make the same file
+        let mut bindings = bindings
+            .iter()
+            .map(|(input, control)| (input.to_string(), format!("{:?}", control)))
+            .collect::<Vec<_>>();
+        bindings.sort();
+        for (input, control) in bindings {
+            writeln!(out, "bind\t{}\t{}", input, control)?;
+        }
+
+        Ok(Self {
+            out,
+            last_mouse: None,
+        })
+    }
+
+    /// Write down this frame if anything happened on it.
+    ///
+    /// Each frame is written straight to disk, so a crash still leaves a useful recording.
+    pub fn write_frame(
+        &mut self,
+        frame: u64,
+        mouse: (f32, f32),
+        events: &[(InputCode, bool)],
+    ) -> io::Result<()> {
+        if events.is_empty() && self.last_mouse == Some(mouse) {
+            return Ok(());
+        }
+        self.last_mouse = Some(mouse);
+
+        write!(self.out, "frame\t{}\t{}\t{}", frame, mouse.0, mouse.1)?;
+        for (input, down) in events {
+            write!(self.out, "\t{}{}", if *down { '+' } else { '-' }, input)?;
+        }
+        writeln!(self.out)?;
+        self.out.flush()
+    }
+}
+
+/// Plays an `InputRecording` back one frame at a time.
+pub(super) struct Replayer {
+    recording: InputRecording,
+    /// Index of the next frame to play
+    next: usize,
+    /// The bindings to go back to once the recording runs out
+    pub player_bindings: AHashMap<InputCode, Control>,
+}
+
+impl Replayer {
+    pub fn new(recording: InputRecording, player_bindings: AHashMap<InputCode, Control>) -> Self {
+        Self {
+            recording,
+            next: 0,
+            player_bindings,
+        }
+    }
+
+    /// The recorded frame for this update, if anything happened on it.
+    pub fn frame(&mut self, frame: u64) -> Option<&InputFrame> {
+        let found = self.recording.frames.get(self.next)?;
+        if found.frame == frame {
+            self.next += 1;
+            Some(found)
+        } else {
+            None
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.next >= self.recording.frames.len()
+    }
+}
@@ -0,0 +1,197 @@
+#![cfg(test)]
+
+use cogs_gamedev::controls::InputHandler;
+
+use super::{record::InputFrame, *};
+
+#[test]
+fn gamepad_buttons() {
+    let mut controls = InputSubscriber::headless(InputSubscriber::default_controls());
+
+    controls.press(InputCode::Gamepad(GamepadButton::South));
+    controls.update();
+    assert!(controls.clicked_down(Control::Select));
+    assert_eq!(
+        controls.just_pressed(),
+        Some(InputCode::Gamepad(GamepadButton::South))
+    );
+
+    controls.update();
+    assert!(!controls.clicked_down(Control::Select));
+    assert!(controls.pressed(Control::Select));
+
+    controls.release(InputCode::Gamepad(GamepadButton::South));
+    controls.update();
+    assert!(!controls.pressed(Control::Select));
+}
+
+#[cfg(feature = "gamepad")]
+#[test]
+fn gamepad_stick() {
+    let mut controls = InputSubscriber::headless(InputSubscriber::default_controls());
+
+    controls.left_stick(0.0, 1.0);
+    controls.update();
+    assert!(controls.clicked_down(Control::Up));
+
+    // Still past the release point, so nothing new happens
+    controls.left_stick(0.1, 0.5);
+    controls.update();
+    assert!(controls.pressed(Control::Up));
+    assert!(!controls.clicked_down(Control::Up));
+
+    controls.left_stick(0.9, 0.0);
+    controls.update();
+    assert!(!controls.pressed(Control::Up));
+    assert!(controls.clicked_down(Control::Right));
+}
+
+#[test]
+fn input_code_names_round_trip() {
+    for input in InputSubscriber::default_controls().keys() {
+        let name = input.to_string();
+        assert_eq!(name.parse::<InputCode>().unwrap(), *input, "{}", name);
+    }
+}
+
+#[test]
+fn controls_save_by_name() {
+    for control in Control::ALL {
+        let data = bincode::serialize(control).unwrap();
+        assert_eq!(bincode::deserialize::<Control>(&data).unwrap(), *control);
+        assert_eq!(data, bincode::serialize(&format!("{:?}", control)).unwrap());
+    }
+}
+
+#[test]
+fn only_changed_bindings_save() {
+    let mut bindings = InputSubscriber::default_controls();
+    bindings.insert(InputCode::Key(KeyCode::U), Control::Undo);
+    bindings.remove(&InputCode::Key(KeyCode::Z));
+
+    let overrides = InputSubscriber::overrides(&bindings);
+    assert_eq!(overrides.len(), 2);
+    assert_eq!(InputSubscriber::with_overrides(&overrides), bindings);
+
+    // Everything the player didn't change keeps its default, even controls added after they saved
+    let mut old = AHashMap::new();
+    old.insert(InputCode::Key(KeyCode::U), Some(Control::Undo));
+    let loaded = InputSubscriber::with_overrides(&old);
+    for (input, control) in InputSubscriber::default_controls() {
+        assert_eq!(loaded[&input], control);
+    }
+}
+
+#[test]
+fn record_and_replay() {
+    let path = std::env::temp_dir().join(format!("wgj_210_record_{}.txt", std::process::id()));
+
+    let mut controls = InputSubscriber::headless(InputSubscriber::default_controls());
+    controls.record_to(&path).unwrap();
+    controls.update();
+    controls.set_mouse((100.0, 50.0));
+    controls.press(InputCode::Mouse(MouseButton::Left));
+    controls.update();
+    controls.update();
+    controls.release(InputCode::Mouse(MouseButton::Left));
+    controls.press(InputCode::Gamepad(GamepadButton::South));
+    controls.update();
+    drop(controls);
+
+    let recording = InputRecording::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(recording.bindings, InputSubscriber::default_controls());
+    assert_eq!(
+        recording.frames,
+        vec![
+            InputFrame {
+                frame: 0,
+                mouse: (0.0, 0.0),
+                events: vec![],
+            },
+            InputFrame {
+                frame: 1,
+                mouse: (100.0, 50.0),
+                events: vec![(InputCode::Mouse(MouseButton::Left), true)],
+            },
+            InputFrame {
+                frame: 3,
+                mouse: (100.0, 50.0),
+                events: vec![
+                    (InputCode::Mouse(MouseButton::Left), false),
+                    (InputCode::Gamepad(GamepadButton::South), true),
+                ],
+            },
+        ]
+    );
+
+    // Playing it back ignores anything fed in by hand
+    let mut controls = InputSubscriber::headless(AHashMap::new());
+    controls.start_replay(recording);
+    controls.update();
+    assert!(controls.is_replaying());
+    controls.press(InputCode::Key(KeyCode::Escape));
+    controls.update();
+    assert!(controls.clicked_down(Control::Click));
+    assert_eq!(controls.mouse_pixel(), (100.0, 50.0));
+    assert!(!controls.pressed(Control::Cancel));
+    controls.update();
+    assert!(controls.pressed(Control::Click));
+    controls.update();
+    assert!(controls.clicked_down(Control::Select));
+    controls.update();
+    assert!(!controls.is_replaying());
+}
+
+#[test]
+fn recording_errors() {
+    assert!(InputRecording::parse("frame\t0\t0\t0").is_err());
+    let err = InputRecording::parse("# wgj_210 input recording\nframe\t2\t0\t0\nframe\t1\t0\t0")
+        .unwrap_err();
+    assert!(format!("{:?}", err).contains("out of order"));
+}
+
+#[test]
+fn touch_gestures() {
+    let mut controls = InputSubscriber::headless(InputSubscriber::default_controls());
+
+    // Tap
+    controls.touch(TouchPhase::Started, 0, (30.0, 40.0));
+    controls.update();
+    assert!(!controls.pressed(Control::Click));
+    controls.touch(TouchPhase::Ended, 0, (31.0, 40.0));
+    controls.update();
+    assert!(controls.clicked_down(Control::Click));
+    assert_eq!(controls.mouse_pixel(), (31.0, 40.0));
+    controls.update();
+    assert!(!controls.pressed(Control::Click));
+
+    // Hold
+    controls.touch(TouchPhase::Started, 1, (50.0, 50.0));
+    for _ in 0..touch::HOLD_UPDATES {
+        controls.update();
+        assert!(!controls.pressed(Control::RightClick));
+    }
+    controls.update();
+    assert!(controls.clicked_down(Control::RightClick));
+    controls.touch(TouchPhase::Ended, 1, (50.0, 50.0));
+    controls.update();
+    assert!(!controls.pressed(Control::RightClick));
+    assert!(!controls.pressed(Control::Click));
+
+    // Drag with one finger, then add another
+    controls.touch(TouchPhase::Started, 2, (50.0, 50.0));
+    controls.touch(TouchPhase::Moved, 2, (60.0, 50.0));
+    controls.update();
+    assert!(controls.clicked_down(Control::Click));
+    controls.touch(TouchPhase::Started, 3, (80.0, 70.0));
+    controls.update();
+    assert!(!controls.pressed(Control::Click));
+    assert!(controls.clicked_down(Control::Pan));
+    assert_eq!(controls.mouse_pixel(), (70.0, 60.0));
+    controls.touch(TouchPhase::Ended, 3, (80.0, 70.0));
+    controls.touch(TouchPhase::Ended, 2, (60.0, 50.0));
+    controls.update();
+    assert!(!controls.pressed(Control::Pan));
+    assert!(!controls.pressed(Control::Click));
+}
@@ -0,0 +1,159 @@
+//! Turning touchscreen fingers into inputs.
+//!
+//! - A quick tap presses and releases `Touch Tap`.
+//! - Holding a finger still presses `Touch Hold` until it lifts.
+//! - Dragging one finger holds `Touch Tap`, like dragging the mouse.
+//! - Two fingers hold `Two Finger Drag`, and the mouse follows the point between them.
+
+use ahash::AHashMap;
+use macroquad::miniquad::TouchPhase;
+
+use super::{InputCode, TouchGesture};
+use crate::UPDATE_DT;
+
+/// How long a finger has to stay still to count as holding.
+pub(super) const HOLD_UPDATES: u64 = (0.5 / UPDATE_DT) as u64;
+/// How far in canvas pixels a finger can wander and still be a tap.
+const TAP_SLOP: f32 = 4.0;
+
+#[derive(Default)]
+pub(super) struct TouchTracker {
+    /// Where each finger is, in canvas pixels
+    fingers: AHashMap<u64, (f32, f32)>,
+    /// The one finger that could turn into a tap, hold or drag
+    press: Option<Press>,
+    /// Inputs to release on the next update, so quick taps still get seen
+    release_later: Vec<InputCode>,
+}
+
+struct Press {
+    id: u64,
+    start: (f32, f32),
+    updates_held: u64,
+    state: PressState,
+}
+
+#[derive(PartialEq, Eq)]
+enum PressState {
+    /// Not sure what this is yet
+    Waiting,
+    /// It moved, so `Touch Tap` is held down
+    Dragging,
+    /// It held still long enough, so `Touch Hold` is held down
+    Holding,
+}
+
+impl TouchTracker {
+    /// Call this at the end of each update. Anything it queues happens on the next one.
+    pub fn tick(&mut self, queue: &mut Vec<(InputCode, bool)>) {
+        queue.extend(self.release_later.drain(..).map(|input| (input, false)));
+
+        if let Some(press) = &mut self.press {
+            press.updates_held += 1;
+            if press.state == PressState::Waiting && press.updates_held >= HOLD_UPDATES {
+                press.state = PressState::Holding;
+                queue.push((InputCode::Touch(TouchGesture::Hold), true));
+            }
+        }
+    }
+
+    /// Handle a finger doing something.
+    ///
+    /// `pos` is already in canvas pixels. This moves `mouse` to wherever the touching is.
+    pub fn event(
+        &mut self,
+        phase: TouchPhase,
+        id: u64,
+        pos: (f32, f32),
+        queue: &mut Vec<(InputCode, bool)>,
+        mouse: &mut (f32, f32),
+    ) {
+        let finger_count = self.fingers.len();
+        match phase {
+            TouchPhase::Started => {
+                self.fingers.insert(id, pos);
+            }
+            TouchPhase::Moved => {
+                if let Some(finger) = self.fingers.get_mut(&id) {
+                    *finger = pos;
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                self.fingers.remove(&id);
+            }
+        }
+
+        match (finger_count, self.fingers.len()) {
+            (0, 1) => {
+                self.press = Some(Press {
+                    id,
+                    start: pos,
+                    updates_held: 0,
+                    state: PressState::Waiting,
+                });
+            }
+            (1, 2) => {
+                // A second finger means this isn't a tap after all
+                if let Some(press) = self.press.take() {
+                    if let Some(held) = press.state.held() {
+                        queue.push((held, false));
+                    }
+                }
+                queue.push((InputCode::Touch(TouchGesture::TwoFinger), true));
+            }
+            (2, 1) => {
+                queue.push((InputCode::Touch(TouchGesture::TwoFinger), false));
+            }
+            _ => {}
+        }
+
+        if self.fingers.len() >= 2 {
+            let count = self.fingers.len() as f32;
+            let (sx, sy) = self
+                .fingers
+                .values()
+                .fold((0.0, 0.0), |(sx, sy), (x, y)| (sx + x, sy + y));
+            *mouse = (sx / count, sy / count);
+            return;
+        }
+
+        let press = match &mut self.press {
+            Some(press) if press.id == id => press,
+            _ => return,
+        };
+        *mouse = pos;
+        match phase {
+            TouchPhase::Moved => {
+                let (dx, dy) = (pos.0 - press.start.0, pos.1 - press.start.1);
+                if press.state == PressState::Waiting && dx.hypot(dy) > TAP_SLOP {
+                    press.state = PressState::Dragging;
+                    queue.push((InputCode::Touch(TouchGesture::Tap), true));
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                match press.state.held() {
+                    Some(held) => queue.push((held, false)),
+                    None if phase == TouchPhase::Ended => {
+                        let tap = InputCode::Touch(TouchGesture::Tap);
+                        queue.push((tap, true));
+                        self.release_later.push(tap);
+                    }
+                    None => {}
+                }
+                self.press = None;
+            }
+            TouchPhase::Started => {}
+        }
+    }
+}
+
+impl PressState {
+    /// The input this is holding down, if any
+    fn held(&self) -> Option<InputCode> {
+        match self {
+            PressState::Waiting => None,
+            PressState::Dragging => Some(InputCode::Touch(TouchGesture::Tap)),
+            PressState::Holding => Some(InputCode::Touch(TouchGesture::Hold)),
+        }
+    }
+}
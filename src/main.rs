@@ -2,6 +2,8 @@
 #![feature(bool_to_option)]
 #![feature(option_result_contains)]
 
+#[cfg(not(target_arch = "wasm32"))]
+mod args;
 mod assets;
 mod boilerplates;
 mod controls;
@@ -10,17 +12,20 @@ mod utils;
 
 mod simulator;
 
-
 // `getrandom` doesn't support WASM so we use quadrand's rng for it.
 #[cfg(target_arch = "wasm32")]
 mod wasm_random_impl;
 
 use crate::{
-    assets::Assets,
-    boilerplates::{FrameInfo, Gamemode},
+    assets::{LogicAssets, RenderAssets},
+    boilerplates::{quit_requested, FrameInfo, ModeStack},
     controls::InputSubscriber,
     modes::ModeLogo,
-    utils::draw::width_height_deficit,
+    utils::{
+        draw::{canvas_camera, width_height_deficit},
+        profile::Profile,
+        settings,
+    },
 };
 
 use macroquad::prelude::*;
@@ -29,8 +34,12 @@ const WIDTH: f32 = 320.0;
 const HEIGHT: f32 = 240.0;
 const ASPECT_RATIO: f32 = WIDTH / HEIGHT;
 
-const UPDATES_PER_DRAW: u64 = 100;
-const UPDATE_DT: f32 = 1.0 / (30.0 * UPDATES_PER_DRAW as f32);
+/// How many times a second the game logic updates, no matter how fast it draws.
+const UPDATES_PER_SECOND: u32 = 60;
+const UPDATE_DT: f32 = 1.0 / UPDATES_PER_SECOND as f32;
+/// When drawing falls behind, at most this many updates run to catch up.
+/// Past that the game just slows down, instead of lurching forwards after a hitch.
+const MAX_CATCHUP_UPDATES: u32 = 5;
 
 /// The `macroquad::main` macro uses this.
 fn window_conf() -> Conf {
@@ -49,6 +58,11 @@ fn window_conf() -> Conf {
 
 #[macroquad::main(window_conf)]
 async fn main() {
+    #[cfg(not(target_arch = "wasm32"))]
+    if args::ARGS.run_profile_commands() {
+        return;
+    }
+
     let loading = Texture2D::from_file_with_format(
         include_bytes!("../assets/textures/title/loading.png"),
         None,
@@ -75,50 +89,84 @@ async fn main() {
     next_frame().await;
     next_frame().await;
 
+    // Load the profile now so it doesn't hitch later
+    Profile::current().settings.apply();
+
     gameloop().await;
 }
 
+/// Load everything, for keeps.
+async fn load_assets() -> (&'static LogicAssets, &'static RenderAssets) {
+    let logic_assets = LogicAssets::init().await;
+    let assets = RenderAssets::init().await;
+    (
+        Box::leak(Box::new(logic_assets)),
+        Box::leak(Box::new(assets)),
+    )
+}
+
+/// Make the input subscriber, recording or replaying if the command line says to.
+fn new_controls() -> InputSubscriber {
+    #[allow(unused_mut)]
+    let mut controls = InputSubscriber::new();
+    #[cfg(not(target_arch = "wasm32"))]
+    args::ARGS.setup_controls(&mut controls);
+    controls
+}
+
 /// Threaded version of main.
 ///
 /// This updates and draws at the same time.
 #[cfg(not(any(target_arch = "wasm32", not(feature = "thread_loop"))))]
 async fn gameloop() {
     use crossbeam::channel::TryRecvError;
-    use std::thread;
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
 
-    let assets = Assets::init().await;
-    let assets = Box::leak(Box::new(assets)) as &'static Assets;
-    let mut controls = InputSubscriber::new();
+    let (logic_assets, assets) = load_assets().await;
+    let mut controls = new_controls();
 
-    let (draw_tx, draw_rx) = crossbeam::channel::bounded(0);
+    let (draw_tx, draw_rx) = crossbeam::channel::unbounded();
 
     // Drawing must happen on the main thread (thanks macroquad...)
     // so updating goes over here
     let _update_handle = thread::spawn(move || {
-        let mut mode_stack: Vec<Box<dyn Gamemode>> = vec![Box::new(ModeLogo::new())];
-        let mut frame_info = FrameInfo {
-            dt: UPDATE_DT,
-            frames_ran: 0,
-        };
+        let mut mode_stack = ModeStack::new(Box::new(ModeLogo::new()));
+        let mut frame_info = FrameInfo::first_update();
+        #[cfg(feature = "gamepad")]
+        let mut gamepad = controls::GamepadPoller::new();
 
+        let step = Duration::from_secs_f32(UPDATE_DT);
+        let mut next_update = Instant::now();
         loop {
+            #[cfg(feature = "gamepad")]
+            gamepad.poll(&mut controls);
             controls.update();
             // Update the current state.
             // To change state, return a non-None transition.
-            let transition = mode_stack
-                .last_mut()
-                .unwrap()
-                .update(&controls, frame_info, assets);
-            transition.apply(&mut mode_stack, assets);
-
-            #[allow(clippy::modulo_one)]
-            if frame_info.frames_ran % UPDATES_PER_DRAW == 0 {
-                let drawer = mode_stack.last_mut().unwrap().get_draw_info();
-                // Wait on the draw thread to finish up drawing, then send.
-                // Ignore the error
-                let _ = draw_tx.send(drawer);
-            }
+            mode_stack.update(&controls, frame_info, logic_assets);
             frame_info.frames_ran += 1;
+
+            // Send along the newest state and when it's from.
+            // If the draw thread is gone we're shutting down anyways
+            if draw_tx
+                .send((mode_stack.get_draw_info(), Instant::now()))
+                .is_err()
+            {
+                return;
+            }
+
+            // Sleep until it's time for the next one
+            next_update += step;
+            let now = Instant::now();
+            if next_update > now {
+                thread::sleep(next_update - now);
+            } else if now - next_update > step * MAX_CATCHUP_UPDATES {
+                // Too far behind to catch up, so forget about it
+                next_update = now;
+            }
         }
     });
 
@@ -129,27 +177,37 @@ async fn gameloop() {
     let mut frame_info = FrameInfo {
         dt: 0.0,
         frames_ran: 0,
+        interpolation: 0.0,
     };
+    // The window always starts out windowed; this catches it up to the setting
+    let mut fullscreen = false;
+    #[cfg(debug_assertions)]
+    let mut watcher = assets::reload::Watcher::new();
+    let mut latest = draw_rx.recv().expect("The draw channel closed!");
     loop {
+        if quit_requested() {
+            return;
+        }
         frame_info.dt = macroquad::time::get_frame_time();
+        settings::update_window(&mut fullscreen);
+        #[cfg(debug_assertions)]
+        watcher.poll(frame_info.dt, logic_assets, assets).await;
 
-        let drawer = match draw_rx.try_recv() {
-            Ok(it) => it,
-            Err(TryRecvError::Empty) => {
-                eprintln!("Waiting on updates!");
-                draw_rx.recv().unwrap()
+        // Skip to the newest state; when there isn't one, draw the last one again
+        loop {
+            match draw_rx.try_recv() {
+                Ok(it) => latest = it,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => panic!("The draw channel closed!"),
             }
-            Err(TryRecvError::Disconnected) => panic!("The draw channel closed!"),
-        };
+        }
+        let (drawer, updated_at) = &latest;
+        frame_info.interpolation = (updated_at.elapsed().as_secs_f32() / UPDATE_DT).min(1.0);
+        assets.play_sounds(&logic_assets.sounds);
 
         // Draw the state.
         push_camera_state();
-        set_camera(&Camera2D {
-            render_target: Some(canvas),
-            zoom: vec2((WIDTH as f32).recip() * 2.0, (HEIGHT as f32).recip() * 2.0),
-            target: vec2(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0),
-            ..Default::default()
-        });
+        set_camera(&canvas_camera(canvas));
 
         clear_background(WHITE);
         drawer.draw(assets, frame_info);
@@ -185,21 +243,35 @@ async fn gameloop() {
 /// Unthreaded version of main.
 #[cfg(any(target_arch = "wasm32", not(feature = "thread_loop")))]
 async fn gameloop() {
-    let assets = Assets::init().await;
-    let assets = Box::leak(Box::new(assets)) as &'static Assets;
+    let (logic_assets, assets) = load_assets().await;
 
-    let mut controls = InputSubscriber::new();
-    let mut mode_stack: Vec<Box<dyn Gamemode>> = vec![Box::new(ModeLogo::new())];
+    let mut controls = new_controls();
+    #[cfg(feature = "gamepad")]
+    let mut gamepad = controls::GamepadPoller::new();
+    let mut mode_stack = ModeStack::new(Box::new(ModeLogo::new()));
 
     let canvas = render_target(WIDTH as u32, HEIGHT as u32);
     canvas.texture.set_filter(FilterMode::Nearest);
 
+    // Updates and draws count their frames separately
+    let mut update_info = FrameInfo::first_update();
     let mut frame_info = FrameInfo {
-        dt: UPDATE_DT,
+        dt: 0.0,
         frames_ran: 0,
+        interpolation: 0.0,
     };
+    // Update time that's passed but hasn't been updated for yet
+    let mut behind = 0.0;
     let mut mouse_entropy = 0.0f64;
+    // The window always starts out windowed; this catches it up to the setting
+    let mut fullscreen = false;
+    #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+    let mut watcher = assets::reload::Watcher::new();
     loop {
+        if quit_requested() {
+            return;
+        }
+        settings::update_window(&mut fullscreen);
         if frame_info.frames_ran <= 300 {
             let (mx, my) = mouse_position();
             // 7919 is the last prime on wikipedia's list of prime numbers
@@ -209,34 +281,31 @@ async fn gameloop() {
             }
         }
 
-        frame_info.dt = UPDATE_DT;
+        frame_info.dt = macroquad::time::get_frame_time();
+        #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+        watcher.poll(frame_info.dt, logic_assets, assets).await;
+        // Don't try to make up for long hitches, like the window getting dragged around
+        behind += frame_info.dt.min(UPDATE_DT * MAX_CATCHUP_UPDATES as f32);
 
-        // Update the current state.
+        // Update the current state, as many times as it's due.
         // To change state, return a non-None transition.
-        for _ in 0..UPDATES_PER_DRAW {
+        while behind >= UPDATE_DT {
+            #[cfg(feature = "gamepad")]
+            gamepad.poll(&mut controls);
             controls.update();
 
-            let transition = mode_stack
-                .last_mut()
-                .unwrap()
-                .update(&controls, frame_info, assets);
-            transition.apply(&mut mode_stack, assets);
+            mode_stack.update(&controls, update_info, logic_assets);
+            update_info.frames_ran += 1;
+            behind -= UPDATE_DT;
         }
-
-        frame_info.dt = macroquad::time::get_frame_time();
+        frame_info.interpolation = behind / UPDATE_DT;
+        assets.play_sounds(&logic_assets.sounds);
 
         push_camera_state();
-        // These divides and multiplies are required to get the camera in the center of the screen
-        // and having it fill everything.
-        set_camera(&Camera2D {
-            render_target: Some(canvas),
-            zoom: vec2((WIDTH as f32).recip() * 2.0, (HEIGHT as f32).recip() * 2.0),
-            target: vec2(WIDTH as f32 / 2.0, HEIGHT as f32 / 2.0),
-            ..Default::default()
-        });
+        set_camera(&canvas_camera(canvas));
         clear_background(WHITE);
         // Draw the state.
-        let drawer = mode_stack.last_mut().unwrap().get_draw_info();
+        let drawer = mode_stack.get_draw_info();
         drawer.draw(assets, frame_info);
 
         // Done rendering to the canvas; go back to our normal camera
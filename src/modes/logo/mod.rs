@@ -1,8 +1,11 @@
 use crate::{
-    assets::Assets,
-    boilerplates::{FrameInfo, Gamemode, GamemodeDrawer, Transition},
+    assets::{LogicAssets, RenderAssets, SoundId},
+    boilerplates::{Effect, FrameInfo, Gamemode, GamemodeDrawer, Transition},
     controls::{Control, InputSubscriber},
-    utils::draw::{self, hexcolor},
+    utils::{
+        draw::{self, hexcolor},
+        profile::Profile,
+    },
     HEIGHT, WIDTH,
 };
 
@@ -13,14 +16,16 @@ use rand::Rng;
 
 use std::f32::consts::TAU;
 
-use super::ModePlaying;
+use super::ModeProfiles;
 
 const BANNER_DISPLAY_SIZE: f32 = WIDTH * 0.6;
 const BANNER_START_TIME: f64 = 0.25;
 
 #[derive(Clone)]
 pub struct ModeLogo {
-    start_time: f64,
+    /// Seconds since the logo showed up.
+    /// This counts update time instead of reading the clock so replays go the same way.
+    time_ran: f64,
     first_frame: bool,
 
     blades: usize,
@@ -67,8 +72,15 @@ impl ModeLogo {
             &mut QuadRand,
         );
 
+        // Spinning things are exactly what reduced motion is for
+        let rotation_speed = if Profile::current().settings.reduced_motion {
+            0.0
+        } else {
+            rotation_speed
+        };
+
         Self {
-            start_time: 0.0,
+            time_ran: 0.0,
             first_frame: true,
 
             blades,
@@ -83,22 +95,24 @@ impl Gamemode for ModeLogo {
     fn update(
         &mut self,
         controls: &InputSubscriber,
-        _frame_info: FrameInfo,
-        assets: &Assets,
+        frame_info: FrameInfo,
+        assets: &LogicAssets,
     ) -> Transition {
         if self.first_frame {
             self.first_frame = false;
-            self.start_time = macroquad::time::get_time();
-            macroquad::audio::play_sound_once(assets.sounds.title_jingle);
+            assets.sounds.play(
+                SoundId::TitleJingle,
+                Profile::current().settings.music_gain(),
+            );
+        } else {
+            self.time_ran += frame_info.dt as f64;
         }
 
-        if macroquad::time::get_time() - self.start_time > 5.0
-            || controls.clicked_down(Control::Click)
-        {
-            macroquad::audio::stop_sound(assets.sounds.title_jingle);
+        if self.time_ran > 5.0 || controls.clicked_down(Control::Click) {
+            assets.sounds.stop(SoundId::TitleJingle);
 
             // Put your next state here!
-            Transition::Swap(Box::new(ModePlaying::new_from_level(0, assets)))
+            Transition::Swap(Box::new(ModeProfiles::new())).animated(Effect::Fade, 0.5)
         } else {
             Transition::None
         }
@@ -111,12 +125,12 @@ impl Gamemode for ModeLogo {
 }
 
 impl GamemodeDrawer for ModeLogo {
-    fn draw(&self, assets: &Assets, _frame_info: FrameInfo) {
+    fn draw(&self, assets: &RenderAssets, frame_info: FrameInfo) {
         use macroquad::prelude::*;
 
         let background = draw::hexcolor(0x21181bff);
 
-        let time_ran = macroquad::time::get_time() - self.start_time;
+        let time_ran = self.time_ran + frame_info.since_update() as f64;
 
         let bg_color = if time_ran < 0.52 {
             background
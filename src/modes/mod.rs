@@ -1,4 +1,12 @@
 mod logo;
 pub use logo::ModeLogo;
+mod pause;
+pub use pause::ModePause;
 mod playing;
 pub use playing::ModePlaying;
+mod profiles;
+pub use profiles::ModeProfiles;
+mod rebind;
+pub use rebind::ModeRebind;
+mod settings;
+pub use settings::ModeSettings;
@@ -0,0 +1,193 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use cogs_gamedev::controls::InputHandler;
+use macroquad::prelude::Vec2;
+
+use crate::{
+    assets::{LogicAssets, RenderAssets},
+    boilerplates::{request_quit, Effect, FrameInfo, Gamemode, GamemodeDrawer, Transition},
+    controls::{Control, InputSubscriber},
+    modes::{ModePlaying, ModeSettings},
+    utils::{
+        draw::hexcolor,
+        menu::Menu,
+        profile::Profile,
+        text::{self, Markup},
+    },
+    HEIGHT, WIDTH,
+};
+
+/// Where the panel goes
+const PANEL_X: f32 = 64.0;
+const PANEL_Y: f32 = 40.0;
+const PANEL_WIDTH: f32 = WIDTH - PANEL_X * 2.0;
+const PANEL_HEIGHT: f32 = HEIGHT - PANEL_Y * 2.0;
+
+/// Menu that goes over a level when the player presses escape.
+#[derive(Clone)]
+pub struct ModePause {
+    menu: Menu,
+    state: PauseState,
+    /// The level being played
+    level_id: String,
+    /// Set to have the level start over once we're gone
+    restart: Arc<AtomicBool>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PauseState {
+    Main,
+    LevelSelect,
+}
+
+impl ModePause {
+    pub fn new(level_id: String, restart: Arc<AtomicBool>) -> Self {
+        Self {
+            menu: Self::main_menu(),
+            state: PauseState::Main,
+            level_id,
+            restart,
+        }
+    }
+
+    fn main_menu() -> Menu {
+        let mut entries = vec!["resume", "restart level", "settings", "level select"];
+        // Browsers don't let pages close themselves
+        if !cfg!(target_arch = "wasm32") {
+            entries.push("quit");
+        }
+        Menu::new(
+            entries.into_iter().map(String::from).collect(),
+            Vec2::new(PANEL_X + 8.0, PANEL_Y + 20.0),
+            PANEL_WIDTH - 16.0,
+        )
+    }
+
+    fn level_menu(&mut self, assets: &LogicAssets) {
+        let profile = Profile::current();
+        let levels = assets.levels();
+        let entries = levels
+            .iter()
+            .map(|level| {
+                let solved = profile
+                    .levels
+                    .get(&level.id)
+                    .is_some_and(|record| record.solved);
+                if solved {
+                    format!("{} (solved)", level.name)
+                } else {
+                    level.name.clone()
+                }
+            })
+            .collect();
+        let mut menu = Menu::new(
+            entries,
+            Vec2::new(PANEL_X + 8.0, PANEL_Y + 20.0),
+            PANEL_WIDTH - 16.0,
+        )
+        .with_rows(((PANEL_HEIGHT - 28.0) / 10.0) as usize);
+        // Start on the one being played
+        menu.selected = levels
+            .iter()
+            .position(|level| level.id == self.level_id)
+            .unwrap_or(0);
+        self.menu = menu;
+        self.state = PauseState::LevelSelect;
+    }
+}
+
+impl Gamemode for ModePause {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        _frame_info: FrameInfo,
+        assets: &LogicAssets,
+    ) -> Transition {
+        let picked = self.menu.update(controls);
+        match self.state {
+            PauseState::Main => {
+                if controls.clicked_down(Control::Cancel) {
+                    return Transition::Pop;
+                }
+                match picked {
+                    Some(0) => return Transition::Pop,
+                    Some(1) => {
+                        self.restart.store(true, Ordering::Relaxed);
+                        return Transition::Pop;
+                    }
+                    Some(2) => {
+                        return Transition::Push(Box::new(ModeSettings::new()))
+                            .animated(Effect::Wipe, 0.3)
+                    }
+                    Some(3) => self.level_menu(assets),
+                    Some(_) => {
+                        request_quit();
+                    }
+                    None => {}
+                }
+            }
+            PauseState::LevelSelect => {
+                if controls.clicked_down(Control::Cancel) {
+                    self.menu = Self::main_menu();
+                    self.state = PauseState::Main;
+                } else if let Some(idx) = picked {
+                    // Take the level out from under us too
+                    return Transition::PopNAndPush(
+                        2,
+                        vec![Box::new(ModePlaying::new_from_level(idx, assets))],
+                    )
+                    .animated(Effect::Dissolve, 0.8);
+                }
+            }
+        }
+
+        Transition::None
+    }
+
+    fn get_draw_info(&mut self) -> Box<dyn GamemodeDrawer> {
+        // I am my own drawer
+        Box::new(self.clone())
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
+
+impl GamemodeDrawer for ModePause {
+    fn draw(&self, assets: &RenderAssets, _frame_info: FrameInfo) {
+        use macroquad::prelude::*;
+
+        draw_rectangle(
+            PANEL_X,
+            PANEL_Y,
+            PANEL_WIDTH,
+            PANEL_HEIGHT,
+            hexcolor(0x21181bff),
+        );
+        draw_rectangle_lines(
+            PANEL_X,
+            PANEL_Y,
+            PANEL_WIDTH,
+            PANEL_HEIGHT,
+            2.0,
+            hexcolor(0x92e8c0ff),
+        );
+
+        let fonts = &assets.textures.fonts;
+        let title = match self.state {
+            PauseState::Main => "paused",
+            PauseState::LevelSelect => "pick a level",
+        };
+        text::draw_wrapped(
+            title,
+            vec2(PANEL_X + 8.0, PANEL_Y + 6.0),
+            PANEL_WIDTH - 16.0,
+            Markup::plain(fonts.medium, WHITE),
+        );
+        self.menu.draw_default(fonts.small);
+    }
+}
@@ -0,0 +1,162 @@
+use cogs_gamedev::grids::{Direction4, ICoord, IRect};
+use macroquad::prelude::{vec2, Rect, Vec2};
+
+use super::{
+    BOARD_ORIGIN_X, BOARD_ORIGIN_Y, SYMBOL_DISPLAY_SIZE, SYMBOL_GAP, VIEW_ACROSS, VIEW_DOWN,
+};
+
+/// The most zoomed in the camera gets.
+pub(super) const MAX_ZOOM: u32 = 3;
+
+/// Which part of the board is on screen, and how big it's drawn.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Camera {
+    /// Position on the board, in zoomed pixels, that shows up at the top-left corner of the view.
+    offset: Vec2,
+    /// How many times bigger than normal everything is drawn.
+    /// This is always a whole number so the symbol art stays crisp.
+    zoom: u32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::ZERO,
+            zoom: 1,
+        }
+    }
+}
+
+impl Camera {
+    /// How many times bigger than normal everything is drawn.
+    pub fn zoom(&self) -> f32 {
+        self.zoom as f32
+    }
+
+    /// Distance between the corners of two neighboring squares, in pixels.
+    pub fn gap(&self) -> f32 {
+        SYMBOL_GAP * self.zoom as f32
+    }
+
+    /// How big a square is drawn, in pixels.
+    pub fn display_size(&self) -> f32 {
+        SYMBOL_DISPLAY_SIZE * self.zoom as f32
+    }
+
+    /// Given a coordinate in world space, get the pixel coordinates of its center.
+    pub fn center(&self, pos: ICoord) -> Vec2 {
+        self.coord_to_px(pos) + Vec2::splat(self.display_size() / 2.0)
+    }
+    /// The part of the screen the board is drawn in.
+    pub fn view() -> Rect {
+        Rect::new(
+            BOARD_ORIGIN_X,
+            BOARD_ORIGIN_Y,
+            SYMBOL_GAP * VIEW_ACROSS as f32,
+            SYMBOL_GAP * VIEW_DOWN as f32,
+        )
+    }
+
+    /// The middle of the view, in pixels.
+    pub fn view_center() -> Vec2 {
+        let view = Self::view();
+        view.point() + view.size() / 2.0
+    }
+
+    /// Given a coordinate in pixel space, get the world space coordinates the pixel lies within.
+    ///
+    /// Pixels outside the view don't point at anything, so they're `None`.
+    pub fn px_to_coord(&self, pos: Vec2) -> Option<ICoord> {
+        if !Self::view().contains(pos) {
+            return None;
+        }
+        // Everything here is a whole number of pixels, so flooring is exact at any zoom
+        let adjust = ((pos - vec2(BOARD_ORIGIN_X, BOARD_ORIGIN_Y) + self.offset.round())
+            / self.gap())
+        .floor();
+        Some(ICoord::new(adjust.x as isize, adjust.y as isize))
+    }
+
+    /// Given a coordinate in world space, get the pixel coordinates of its top-left corner.
+    pub fn coord_to_px(&self, pos: ICoord) -> Vec2 {
+        vec2(pos.x as f32, pos.y as f32) * self.gap() - self.offset.round()
+            + vec2(BOARD_ORIGIN_X, BOARD_ORIGIN_Y)
+    }
+
+    /// Given a coordinate in pixel space, get the world space coordinates the pixel lies within
+    /// and which side of that square it's closest to.
+    pub fn px_to_edge(&self, pos: Vec2) -> Option<(ICoord, Direction4)> {
+        let coord = self.px_to_coord(pos)?;
+        let offset = pos - self.coord_to_px(coord) - Vec2::splat(self.gap() / 2.0);
+        let dir = if offset.x.abs() > offset.y.abs() {
+            if offset.x > 0.0 {
+                Direction4::East
+            } else {
+                Direction4::West
+            }
+        } else if offset.y > 0.0 {
+            Direction4::South
+        } else {
+            Direction4::North
+        };
+        Some((coord, dir))
+    }
+
+    /// Slide the view by this many pixels, without going past the edges of the board.
+    pub fn pan(&mut self, delta: Vec2, bounds: IRect) {
+        self.offset += delta;
+        self.clamp(bounds);
+    }
+
+    /// Zoom in (positive) or out (negative) this many steps,
+    /// keeping the spot under `anchor` in the same place on screen.
+    pub fn zoom_by(&mut self, steps: i32, anchor: Vec2, bounds: IRect) {
+        let zoom = (self.zoom as i32 + steps).clamp(1, MAX_ZOOM as i32) as u32;
+        if zoom == self.zoom {
+            return;
+        }
+        let anchor = anchor - vec2(BOARD_ORIGIN_X, BOARD_ORIGIN_Y);
+        let on_board = (anchor + self.offset) / self.zoom as f32;
+        self.zoom = zoom;
+        self.offset = on_board * zoom as f32 - anchor;
+        self.clamp(bounds);
+    }
+
+    /// Scroll just enough that this square is in view.
+    pub fn show(&mut self, pos: ICoord, bounds: IRect) {
+        let corner = vec2(pos.x as f32, pos.y as f32) * self.gap();
+        let view = Self::view();
+        self.offset = self
+            .offset
+            .max(corner + Vec2::splat(self.gap()) - view.size());
+        self.offset = self.offset.min(corner);
+        self.clamp(bounds);
+    }
+
+    fn clamp(&mut self, bounds: IRect) {
+        let board_size = vec2(bounds.width as f32, bounds.height as f32) * self.gap();
+        let max = (board_size - Self::view().size()).max(Vec2::ZERO);
+        self.offset = self.offset.max(Vec2::ZERO).min(max);
+    }
+
+    /// Every square that's at least partly on screen.
+    pub fn visible(&self, bounds: IRect) -> impl Iterator<Item = ICoord> {
+        let view = Self::view();
+        let start = (self.offset / self.gap()).floor();
+        let end = ((self.offset + view.size()) / self.gap()).ceil();
+        let xs = (start.x as isize).max(bounds.left)
+            ..(end.x as isize).min(bounds.left + bounds.width as isize);
+        let ys = (start.y as isize).max(bounds.top)
+            ..(end.y as isize).min(bounds.top + bounds.height as isize);
+        xs.flat_map(move |x| ys.clone().map(move |y| ICoord::new(x, y)))
+    }
+
+    /// How far along the board the view is and how much of it fits, from 0 to 1 on each axis.
+    ///
+    /// Used for the scrollbars.
+    pub fn scroll_fraction(&self, bounds: IRect) -> (Vec2, Vec2) {
+        let board_size = vec2(bounds.width as f32, bounds.height as f32) * self.gap();
+        let shown = (Self::view().size() / board_size).min(Vec2::ONE);
+        (self.offset / board_size, shown)
+    }
+}
@@ -1,90 +1,434 @@
 use ahash::{AHashMap, AHashSet};
-use cogs_gamedev::grids::ICoord;
+use cogs_gamedev::grids::{Direction4, ICoord, IRect};
+use itertools::Itertools;
 use macroquad::prelude::*;
 
 use crate::{
-    assets::Assets,
+    assets::RenderAssets,
     boilerplates::{FrameInfo, GamemodeDrawer},
     simulator::{
-        board::Board,
-        symbols::{Symbol, SYMBOL_DISPLAY_SIZE},
+        board::{Board, Fragment, GrammarReport},
+        levels::RevealedRules,
+        symbols::{PartOfSpeech, Symbol, SYMBOL_SIZE},
+    },
+    utils::{
+        settings::Palette,
+        text::{self, Markup},
     },
-    utils::draw::{hexcolor, mouse_position_pixel},
     HEIGHT, WIDTH,
 };
 
-use super::{
-    coord_to_px, px_to_coord, SelectState, BOARD_ORIGIN_X, BOARD_ORIGIN_Y, SYMBOLS_ACROSS,
-    SYMBOLS_DOWN, SYMBOL_GAP,
-};
+use super::{camera::Camera, SelectState, Tool, BOARD_ORIGIN_X, BOARD_ORIGIN_Y};
 
 pub(super) struct Drawer {
     pub board: Board,
     pub symbol_indices: AHashMap<u32, usize>,
 
     pub selection: SelectState,
+    /// The symbol under the mouse, for the inspector
+    pub hovered: Option<Symbol>,
+    /// The fragment the player right-clicked on for info
+    pub inspected: Option<Fragment>,
+    pub reveal: RevealedRules,
 
     pub valid_poses: AHashSet<ICoord>,
+    /// If the explain overlay is on, what to explain.
+    pub explain: Option<GrammarReport>,
     pub won: bool,
+
+    pub tool: Tool,
+    pub glues_left: u32,
+    pub cuts_left: u32,
+    pub hovered_edge: (ICoord, Direction4),
+    /// Where the keyboard cursor is, if the keyboard is in use
+    pub cursor: Option<ICoord>,
+    /// Where to draw the origin square of the held fragment
+    pub held_at: Vec2,
+    pub bounds: IRect,
+    pub camera: Camera,
+    pub palette: Palette,
+    /// What's wrong with the level file, if it changed and didn't load
+    pub load_error: Option<String>,
 }
 
 impl GamemodeDrawer for Drawer {
-    fn draw(&self, assets: &Assets, frame_info: FrameInfo) {
+    fn draw(&self, assets: &RenderAssets, frame_info: FrameInfo) {
+        assets.stitch_atlas(&self.symbol_indices);
         clear_background(BLACK);
 
+        let view = Camera::view();
         draw_rectangle(
-            BOARD_ORIGIN_X - 2.0,
-            BOARD_ORIGIN_Y - 2.0,
-            SYMBOL_GAP * SYMBOLS_ACROSS as f32 + 2.0,
-            SYMBOL_GAP * SYMBOLS_DOWN as f32 + 2.0,
+            view.x - 2.0,
+            view.y - 2.0,
+            view.w + 2.0,
+            view.h + 2.0,
             WHITE,
         );
 
-        for symbol_x in 0..SYMBOLS_ACROSS {
-            for symbol_y in 0..SYMBOLS_DOWN {
-                let pos = ICoord::new(symbol_x as _, symbol_y as _);
-                let corner = coord_to_px(pos);
-
-                draw_rectangle(
-                    corner.x,
-                    corner.y,
-                    SYMBOL_DISPLAY_SIZE,
-                    SYMBOL_DISPLAY_SIZE,
-                    hexcolor(0x92e8c0_ff),
-                );
+        let size = self.camera.display_size();
+        for pos in self.camera.visible(self.bounds) {
+            let corner = self.camera.coord_to_px(pos);
 
-                if let Some(here) = self.board.symbols.get(&pos) {
-                    let idx = *self
-                        .symbol_indices
-                        .get(&here.code)
-                        .ok_or_else(|| {
-                            format!("{:?} at {} didn't have an entry in the atlas", here, pos)
-                        })
-                        .unwrap();
-
-                    let color = if self.valid_poses.contains(&pos) {
-                        hexcolor(0x6abe30_ff) // green
-                    } else {
-                        hexcolor(0xac3232_ff) // red
-                    };
-
-                    here.draw(corner, idx, color, assets);
-                }
+            draw_rectangle(corner.x, corner.y, size, size, self.palette.square);
+
+            if let Some(here) = self.board.symbols.get(&pos) {
+                let idx = *self
+                    .symbol_indices
+                    .get(&here.code)
+                    .ok_or_else(|| {
+                        format!("{:?} at {} didn't have an entry in the atlas", here, pos)
+                    })
+                    .unwrap();
+
+                let color = if self.valid_poses.contains(&pos) {
+                    self.palette.good
+                } else {
+                    self.palette.bad
+                };
+
+                here.draw_sized(corner, size, idx, color, assets);
             }
         }
 
+        self.draw_fragment_edges();
+
+        if let Some(report) = &self.explain {
+            self.draw_explain(report);
+        }
+        self.clip_to_view();
+        self.draw_panel(assets);
+
+        if let Some(cursor) = self.cursor {
+            let corner = self.camera.coord_to_px(cursor);
+            draw_rectangle_lines(
+                corner.x - 2.0,
+                corner.y - 2.0,
+                size + 4.0,
+                size + 4.0,
+                2.0,
+                self.palette.highlight,
+            );
+        }
+
         if let SelectState::HoldingFragment { origin, symbols } = &self.selection {
-            let (mx, my) = mouse_position_pixel();
             for (pos, sym) in symbols {
                 let zero_pos = *pos + ICoord::new(-origin.x, -origin.y);
-                let corner = vec2(zero_pos.x as f32, zero_pos.y as f32) * SYMBOL_GAP + vec2(mx, my)
-                    - Vec2::splat(SYMBOL_DISPLAY_SIZE / 2.0);
+                let corner = vec2(zero_pos.x as f32, zero_pos.y as f32) * self.camera.gap()
+                    + self.held_at
+                    - Vec2::splat(size / 2.0);
                 let corner = corner.round();
 
                 let idx = self.symbol_indices[&sym.code];
 
-                sym.draw(corner, idx, hexcolor(0x14182e_ff), assets);
+                sym.draw_sized(corner, size, idx, self.palette.ink, assets);
             }
         }
+
+        if let Some(problem) = &self.load_error {
+            draw_rectangle(0.0, 0.0, WIDTH, 34.0, Color::new(0.0, 0.0, 0.0, 0.8));
+            text::draw_wrapped(
+                &format!("couldn't reload the level: {}", problem),
+                vec2(4.0, 4.0),
+                WIDTH - 8.0,
+                Markup::plain(assets.textures.fonts.small, self.palette.bad),
+            );
+        }
+    }
+}
+
+impl Drawer {
+    /// Cover up everything that spilled outside the view, and draw scrollbars if it scrolls.
+    fn clip_to_view(&self) {
+        let view = Camera::view();
+        let (left, top) = (view.x - 2.0, view.y - 2.0);
+        let (right, bottom) = (view.right(), view.bottom());
+        draw_rectangle(0.0, 0.0, WIDTH, top, BLACK);
+        draw_rectangle(0.0, bottom, WIDTH, HEIGHT - bottom, BLACK);
+        draw_rectangle(0.0, top, left, bottom - top, BLACK);
+        draw_rectangle(right, top, WIDTH - right, bottom - top, BLACK);
+
+        let (start, shown) = self.camera.scroll_fraction(self.bounds);
+        let color = self.palette.scrollbar;
+        if shown.x < 1.0 {
+            draw_rectangle(
+                view.x + view.w * start.x,
+                bottom + 2.0,
+                view.w * shown.x,
+                2.0,
+                color,
+            );
+        }
+        if shown.y < 1.0 {
+            draw_rectangle(
+                right + 2.0,
+                view.y + view.h * start.y,
+                2.0,
+                view.h * shown.y,
+                color,
+            );
+        }
+    }
+
+    /// Outline each fragment, and show what the glue or cut tool would do.
+    fn draw_fragment_edges(&self) {
+        let fragment_ids: AHashMap<ICoord, usize> = self
+            .board
+            .fragments
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, frag)| frag.iter().map(move |pos| (*pos, idx)))
+            .collect();
+
+        for (&pos, &idx) in fragment_ids.iter() {
+            for dir in Direction4::DIRECTIONS {
+                if fragment_ids.get(&(pos + dir)) != Some(&idx) {
+                    let edge = edge_rect(self.camera, pos, dir);
+                    draw_rectangle(edge.x, edge.y, edge.w, edge.h, self.palette.ink);
+                }
+            }
+        }
+
+        if let Some(inspected) = &self.inspected {
+            for &pos in inspected.iter() {
+                for dir in Direction4::DIRECTIONS {
+                    if !inspected.contains(&(pos + dir)) {
+                        let edge = edge_rect(self.camera, pos, dir);
+                        draw_rectangle(edge.x, edge.y, edge.w, edge.h, self.palette.info);
+                    }
+                }
+            }
+        }
+
+        let (pos, dir) = self.hovered_edge;
+        let (highlight, color) = match self.tool {
+            Tool::Move => return,
+            Tool::Glue => {
+                let here = fragment_ids.get(&pos);
+                let there = fragment_ids.get(&(pos + dir));
+                let edges = if here.is_some() && there.is_some() && here != there {
+                    vec![(pos, dir)]
+                } else {
+                    Vec::new()
+                };
+                (edges, self.palette.highlight)
+            }
+            Tool::Cut => {
+                let edges = match (fragment_ids.get(&pos), fragment_ids.get(&(pos + dir))) {
+                    (Some(here), Some(there)) if here == there => {
+                        // Highlight the whole line the cut will go along
+                        let deltas = dir.deltas();
+                        let line = pos.x * deltas.x + pos.y * deltas.y;
+                        self.board.fragments[*here]
+                            .iter()
+                            .filter(|cell| {
+                                cell.x * deltas.x + cell.y * deltas.y == line
+                                    && fragment_ids.get(&(**cell + dir)) == Some(here)
+                            })
+                            .map(|cell| (*cell, dir))
+                            .collect()
+                    }
+                    _ => Vec::new(),
+                };
+                (edges, self.palette.cut)
+            }
+        };
+        for (pos, dir) in highlight {
+            let edge = edge_rect(self.camera, pos, dir);
+            draw_rectangle(edge.x, edge.y, edge.w, edge.h, color);
+        }
+    }
+
+    /// Draw each sentence's spine with an arrow, and mark where parsing failed.
+    fn draw_explain(&self, report: &GrammarReport) {
+        for sentence in report.sentences.iter() {
+            let color = if sentence.result.is_ok() {
+                self.palette.sentence
+            } else {
+                self.palette.problem
+            };
+
+            if let (Some(dir), Some(end)) = (sentence.dir, sentence.spine.last()) {
+                let start = self.camera.center(sentence.origin);
+                let end = self.camera.center(*end);
+                let zoom = self.camera.zoom();
+                draw_line(start.x, start.y, end.x, end.y, zoom, color);
+
+                let deltas = dir.deltas();
+                let forward = vec2(deltas.x as f32, deltas.y as f32) * zoom;
+                let side = vec2(-forward.y, forward.x);
+                draw_triangle(
+                    end + forward * 5.0,
+                    end + forward + side * 3.0,
+                    end + forward - side * 3.0,
+                    color,
+                );
+            }
+
+            if let Err(oh_no) = &sentence.result {
+                if let Some(pos) = oh_no.pos {
+                    let corner = self.camera.coord_to_px(pos);
+                    let size = self.camera.display_size();
+                    draw_rectangle_lines(
+                        corner.x - 1.0,
+                        corner.y - 1.0,
+                        size + 2.0,
+                        size + 2.0,
+                        2.0,
+                        color,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Draw the strip to the left of the board.
+    ///
+    /// The inspector goes on top, and the explain overlay's reasons go under it.
+    fn draw_panel(&self, assets: &RenderAssets) {
+        const PANEL_WIDTH: f32 = BOARD_ORIGIN_X - 4.0;
+        const GLYPH_SCALE: f32 = 10.0;
+
+        let font = assets.textures.fonts.small;
+        let mut y = BOARD_ORIGIN_Y;
+
+        if let Some(hovered) = &self.hovered {
+            let glyph_size = SYMBOL_SIZE as f32 * GLYPH_SCALE;
+            let corner = vec2((BOARD_ORIGIN_X - glyph_size) / 2.0, y).round();
+            draw_rectangle(
+                corner.x - 2.0,
+                corner.y - 2.0,
+                glyph_size + 4.0,
+                glyph_size + 4.0,
+                self.palette.square,
+            );
+            let idx = self.symbol_indices[&hovered.code];
+            hovered.draw_sized(corner, glyph_size, idx, self.palette.ink, assets);
+            y += glyph_size + 6.0;
+
+            for line in self.classify(hovered.part_of_speech) {
+                y = text::draw_wrapped(
+                    &line,
+                    vec2(2.0, y),
+                    PANEL_WIDTH,
+                    Markup::plain(font, WHITE),
+                );
+            }
+            y += 6.0;
+        }
+
+        if let Some(inspected) = &self.inspected {
+            let (min_x, max_x) = inspected
+                .iter()
+                .map(|pos| pos.x)
+                .minmax()
+                .into_option()
+                .unwrap_or_default();
+            let (min_y, max_y) = inspected
+                .iter()
+                .map(|pos| pos.y)
+                .minmax()
+                .into_option()
+                .unwrap_or_default();
+            let in_sentences = inspected
+                .iter()
+                .filter(|pos| self.valid_poses.contains(*pos))
+                .count();
+            let info = format!(
+                "fragment:\n{} symbols\n{}x{} big\n{} in good sentences",
+                inspected.len(),
+                max_x - min_x + 1,
+                max_y - min_y + 1,
+                in_sentences
+            );
+            y = text::draw_wrapped(
+                &info,
+                vec2(2.0, y),
+                PANEL_WIDTH,
+                Markup::plain(font, self.palette.info),
+            ) + 6.0;
+        }
+
+        // The tool counts go along the bottom, and everything else has to stop above them
+        let show_tools = self.glues_left > 0 || self.cuts_left > 0 || self.tool != Tool::Move;
+        let tools_top = if show_tools {
+            HEIGHT - 2.0 * 6.0 - 4.0
+        } else {
+            HEIGHT
+        };
+        if show_tools {
+            let tools = [
+                (Tool::Glue, "glue", self.glues_left),
+                (Tool::Cut, "cut", self.cuts_left),
+            ];
+            let mut tool_y = tools_top;
+            for (tool, name, left) in tools {
+                let color = if tool == self.tool {
+                    self.palette.highlight
+                } else {
+                    WHITE
+                };
+                text::draw_wrapped(
+                    &format!("{}: {}", name, left),
+                    vec2(2.0, tool_y),
+                    PANEL_WIDTH,
+                    Markup::plain(font, color),
+                );
+                tool_y += 6.0;
+            }
+        }
+
+        if let Some(report) = &self.explain {
+            if report.errors.is_empty() {
+                y = text::draw_wrapped(
+                    "no problems!",
+                    vec2(2.0, y),
+                    PANEL_WIDTH,
+                    Markup::plain(font, self.palette.good),
+                ) + 3.0;
+            }
+            let markup = Markup::plain(font, self.palette.problem);
+            for oh_no in report.errors.iter() {
+                // Leave off the ones that don't fit
+                if y + text::wrapped_height(&oh_no.reason, PANEL_WIDTH, markup) > tools_top {
+                    break;
+                }
+                y = text::draw_wrapped(&oh_no.reason, vec2(2.0, y), PANEL_WIDTH, markup) + 3.0;
+            }
+        }
+    }
+
+    /// Lines describing a part of speech, as much as this level lets on.
+    fn classify(&self, pos: PartOfSpeech) -> Vec<String> {
+        let mut out = Vec::new();
+        if self.reveal.part_of_speech {
+            out.push(String::from(match pos {
+                PartOfSpeech::ParticleStart => "start particle",
+                PartOfSpeech::ParticleCollate => "collator particle",
+                PartOfSpeech::Noun { .. } => "noun",
+                PartOfSpeech::Verb { .. } => "verb",
+            }));
+        }
+        if let PartOfSpeech::Noun { islands, depth } | PartOfSpeech::Verb { islands, depth } = pos {
+            if self.reveal.islands {
+                out.push(format!("islands: {}", islands));
+            }
+            if self.reveal.depth {
+                out.push(format!("modifier depth: {}", depth));
+            }
+        }
+        out
+    }
+}
+
+/// The rectangle of gutter between the square at `pos` and its neighbor in `dir`.
+fn edge_rect(camera: Camera, pos: ICoord, dir: Direction4) -> Rect {
+    let corner = camera.coord_to_px(pos);
+    let size = camera.display_size();
+    let gutter = camera.gap() - size;
+    match dir {
+        Direction4::North => Rect::new(corner.x, corner.y - gutter, size, gutter),
+        Direction4::South => Rect::new(corner.x, corner.y + size, size, gutter),
+        Direction4::West => Rect::new(corner.x - gutter, corner.y, gutter, size),
+        Direction4::East => Rect::new(corner.x + size, corner.y, gutter, size),
     }
 }
@@ -1,48 +1,142 @@
+mod camera;
 mod draw;
 
-use std::mem;
+use std::{
+    mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
 
 use ahash::{AHashMap, AHashSet};
+use anyhow::bail;
 use cogs_gamedev::{
     controls::InputHandler,
-    grids::{ICoord, IRect},
+    grids::{Direction4, ICoord, IRect},
 };
 use itertools::Itertools;
-use macroquad::prelude::{vec2, Vec2};
+use macroquad::prelude::{vec2, warn, Vec2};
 use smallvec::SmallVec;
 
 use crate::{
-    assets::Assets,
-    boilerplates::{FrameInfo, Gamemode, GamemodeDrawer, Transition},
+    assets::LogicAssets,
+    boilerplates::{debug, Effect, FrameInfo, Gamemode, GamemodeDrawer, Transition},
     controls::{Control, InputSubscriber},
+    modes::{ModePause, ModeSettings},
     simulator::{
-        board::Board,
-        symbols::{Symbol, SYMBOL_GAP},
+        board::{Board, Fragment, GrammarReport},
+        levels::RevealedRules,
+        symbols::{Symbol, SYMBOL_DISPLAY_SIZE, SYMBOL_GAP},
+    },
+    utils::{
+        clipboard,
+        profile::{InProgress, Profile},
+        settings::{InputMode, Settings},
     },
-    utils::draw::mouse_position_pixel,
     HEIGHT, WIDTH,
 };
 
-use self::draw::Drawer;
+use self::{camera::Camera, draw::Drawer};
 
 /// Place to start drawing the board from
 const BOARD_ORIGIN_X: f32 = 80.0;
 const BOARD_ORIGIN_Y: f32 = 12.0;
 
-const SYMBOLS_ACROSS: usize = 13;
-const SYMBOLS_DOWN: usize = 13;
+/// How many squares fit in the view at once
+const VIEW_ACROSS: usize = 13;
+const VIEW_DOWN: usize = 13;
+
+/// How long a dropped fragment takes to fly back home, in seconds
+const RETURN_TIME: f32 = 0.15;
 
 pub struct ModePlaying {
     level_id: String,
     board: Board,
+    /// Where symbols are allowed to go
+    bounds: IRect,
+    camera: Camera,
+    /// What the symbol inspector is allowed to show
+    reveal: RevealedRules,
 
     /// Mapping of symbol codes to atlas indices
     symbol_indices: AHashMap<u32, usize>,
 
     selection: SelectState,
+    /// The square the mouse was over last update
+    hovered_coord: ICoord,
+    /// A square in the fragment the player right-clicked on for info
+    inspected: Option<ICoord>,
+
+    /// Where the keyboard cursor is, if the player is using the keyboard.
+    /// When this is `Some` it's used instead of the mouse.
+    cursor: Option<ICoord>,
+    /// The direction the cursor last moved in.
+    /// With the keyboard, gluing and cutting happen on the edge the cursor just crossed.
+    facing: Direction4,
+    /// Index of the fragment the cursor last cycled to
+    cycle_idx: usize,
+    /// Where the mouse was last update, to notice when it moves
+    last_mouse: Vec2,
 
     valid_poses: AHashSet<ICoord>,
+    /// The last grammar check, kept around to explain errors to the player.
+    grammar: GrammarReport,
+    /// Are we drawing the explain-the-error overlay?
+    explain: bool,
     won: bool,
+    /// Seconds spent on the level before winning
+    time_spent: f32,
+    /// How many times the explain overlay has been turned on
+    hints_used: u32,
+
+    /// What clicking on the board does right now
+    tool: Tool,
+    glues_left: u32,
+    cuts_left: u32,
+    /// The edge the mouse was nearest last update, for gluing and cutting
+    hovered_edge: (ICoord, Direction4),
+
+    /// Board states to go back to, most recent last
+    history: Vec<Snapshot>,
+    /// Moves made before the saved board was loaded, which can't be undone
+    moves_before: u32,
+    /// How the level started, for restarting
+    original: Snapshot,
+
+    /// The player's settings, as of the last time they could have changed
+    settings: Settings,
+    /// If the player dropped the held fragment somewhere it can't go,
+    /// where it was dropped and how long it's been flying back.
+    returning: Option<(Vec2, f32)>,
+    /// Set by the pause menu to start over when we come back
+    restart_asked: Arc<AtomicBool>,
+
+    /// How many level reloads we've caught up with
+    reloads_seen: u32,
+    /// What was wrong with the level file, if it changed and didn't load
+    load_error: Option<String>,
+    /// What happened with the last debug command
+    debug_message: Option<String>,
+}
+
+/// What clicking on the board does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tool {
+    /// Pick up and place fragments
+    Move,
+    /// Stick two touching fragments together at the edge between them
+    Glue,
+    /// Split a fragment along a grid line
+    Cut,
+}
+
+/// Everything undoing needs to put back.
+#[derive(Debug, Clone)]
+struct Snapshot {
+    board: Board,
+    glues_left: u32,
+    cuts_left: u32,
 }
 
 #[derive(Debug, Clone)]
@@ -70,23 +164,94 @@ impl SelectState {
 }
 
 impl ModePlaying {
-    pub fn new_from_level(idx: usize, assets: &Assets) -> Self {
-        let level = &assets.levels[idx];
+    pub fn new_from_level(idx: usize, assets: &LogicAssets) -> Self {
+        let levels = assets.levels();
+        let level = &levels[idx];
         let board = level.original_board.clone();
-        let symbol_indices =
-            Symbol::stitch_atlas(board.symbols.values().map(|sym| sym.code), assets);
+        let symbol_indices = Symbol::atlas_indices(board.symbols.values().map(|sym| sym.code));
 
+        let original = Snapshot {
+            board: board.clone(),
+            glues_left: level.glues,
+            cuts_left: level.cuts,
+        };
         let mut out = Self {
             level_id: level.id.clone(),
             board,
+            bounds: IRect {
+                left: 0,
+                top: 0,
+                width: level.width,
+                height: level.height,
+            },
+            camera: Camera::default(),
+            reveal: level.reveal,
             symbol_indices,
             selection: SelectState::None,
+            hovered_coord: ICoord::new(-1, -1),
+            inspected: None,
+            cursor: None,
+            facing: Direction4::East,
+            cycle_idx: 0,
+            last_mouse: Vec2::ZERO,
             valid_poses: AHashSet::new(),
+            grammar: GrammarReport::default(),
+            explain: false,
             won: false,
+            time_spent: 0.0,
+            hints_used: 0,
+            tool: Tool::Move,
+            glues_left: level.glues,
+            cuts_left: level.cuts,
+            hovered_edge: (ICoord::new(-1, -1), Direction4::North),
+            history: Vec::new(),
+            moves_before: 0,
+            original,
+            settings: Profile::current().settings.clone(),
+            returning: None,
+            restart_asked: Arc::new(AtomicBool::new(false)),
+            reloads_seen: assets.reloads.count(),
+            load_error: None,
+            debug_message: None,
         };
+
+        let saved = Profile::current()
+            .levels
+            .get(&level.id)
+            .and_then(|record| record.in_progress.clone());
+        if let Some(saved) = saved {
+            if let Err(oh_no) = out.resume(&saved) {
+                warn!(
+                    "Couldn't pick up where {} left off, starting fresh\n{:?}",
+                    level.id, oh_no
+                );
+            }
+        }
+
         out.check_grammar();
         out
     }
+
+    /// Load a board the player left partway through.
+    fn resume(&mut self, saved: &InProgress) -> anyhow::Result<()> {
+        let board = Board::load(&saved.board, &self.original.board)?;
+        if let Some(pos) = board
+            .symbols
+            .keys()
+            .find(|pos| !self.bounds.contains(**pos))
+        {
+            bail!("{} is off the board", pos);
+        }
+
+        self.board = board;
+        // The level might have gotten stingier since
+        self.glues_left = saved.glues_left.min(self.original.glues_left);
+        self.cuts_left = saved.cuts_left.min(self.original.cuts_left);
+        self.moves_before = saved.moves;
+        self.time_spent = saved.time_spent;
+        self.hints_used = saved.hints_used;
+        Ok(())
+    }
 }
 
 impl Gamemode for ModePlaying {
@@ -94,21 +259,178 @@ impl Gamemode for ModePlaying {
         &mut self,
         controls: &InputSubscriber,
         frame_info: FrameInfo,
-        assets: &Assets,
+        assets: &LogicAssets,
     ) -> Transition {
-        let (mx, my) = mouse_position_pixel();
+        if let Some(res) = assets.reloads.check(&self.level_id, &mut self.reloads_seen) {
+            self.reloaded(res, assets);
+        }
 
-        let hovered_coord = px_to_coord(vec2(mx, my));
+        let (mx, my) = controls.mouse_pixel();
+        let mouse = vec2(mx, my);
 
-        if controls.clicked_down(Control::Debug) {
-            dbg!(self.board.symbols.get(&hovered_coord), &self.selection);
+        if !self.won {
+            self.time_spent += frame_info.dt;
+        }
+
+        if let Some((_, time)) = &mut self.returning {
+            // Hands off while it flies home
+            *time += frame_info.dt;
+            if *time >= RETURN_TIME {
+                self.returning = None;
+                self.cancel_hold();
+                self.check_grammar();
+            }
+            return Transition::None;
+        }
+
+        // Drag the board around.
+        // Wait an update after pressing, because two-finger touches move the mouse when they start.
+        let panning = [Control::Pan, Control::RightClick]
+            .iter()
+            .any(|&ctrl| controls.pressed(ctrl) && !controls.clicked_down(ctrl));
+        if panning {
+            self.camera.pan(self.last_mouse - mouse, self.bounds);
+        }
+
+        // Touching the mouse takes control back from the keyboard
+        if mouse != self.last_mouse || controls.clicked_down(Control::Click) {
+            self.cursor = None;
+        }
+        self.last_mouse = mouse;
+        self.update_cursor(controls, mouse);
+        // Zoom in on the keyboard cursor, or the mouse, or failing that the middle
+        let zoom = controls.clicked_down(Control::ZoomIn) as i32
+            - controls.clicked_down(Control::ZoomOut) as i32;
+        if zoom != 0 {
+            let view = Camera::view();
+            let anchor = match self.cursor {
+                Some(cursor) => self.camera.center(cursor),
+                None if view.contains(mouse) => mouse,
+                None => Camera::view_center(),
+            };
+            self.camera.zoom_by(zoom, anchor, self.bounds);
+        }
+        if let Some(cursor) = self.cursor {
+            self.camera.show(cursor, self.bounds);
+        }
+
+        let hovered_coord = self
+            .cursor
+            .or_else(|| self.camera.px_to_coord(mouse))
+            .unwrap_or_else(|| ICoord::new(-1, -1));
+        self.hovered_coord = hovered_coord;
+        self.hovered_edge = match self.cursor {
+            Some(cursor) => (cursor - self.facing.deltas(), self.facing),
+            None => self
+                .camera
+                .px_to_edge(mouse)
+                .unwrap_or((ICoord::new(-1, -1), Direction4::North)),
+        };
+        let clicked =
+            controls.clicked_down(Control::Click) || controls.clicked_down(Control::Select);
+        // With drag and drop, letting go of the mouse is what places
+        let dropped = controls.clicked_up(Control::Click) && self.cursor.is_none();
+        let drag_to_place = self.settings.input_mode == InputMode::DragAndDrop;
+        let place = if drag_to_place {
+            dropped || controls.clicked_down(Control::Select)
+        } else {
+            clicked
+        };
+
+        if controls.clicked_down(Control::DumpBoard) && debug::shown() {
+            self.dump_board(assets);
+        }
+        if controls.clicked_down(Control::Explain) {
+            self.explain = !self.explain;
+            if self.explain {
+                self.hints_used += 1;
+            }
+        }
+        if controls.clicked_down(Control::OpenSettings) && self.selection.is_none() {
+            return Transition::Push(Box::new(ModeSettings::new())).animated(Effect::Wipe, 0.3);
         }
 
         let mut check_grammar = false;
 
+        if self.selection.is_none() {
+            if controls.clicked_down(Control::Glue) && self.glues_left > 0 {
+                self.tool = if self.tool == Tool::Glue {
+                    Tool::Move
+                } else {
+                    Tool::Glue
+                };
+            }
+            if controls.clicked_down(Control::Cut) && self.cuts_left > 0 {
+                self.tool = if self.tool == Tool::Cut {
+                    Tool::Move
+                } else {
+                    Tool::Cut
+                };
+            }
+            if controls.clicked_down(Control::Undo) {
+                if let Some(snapshot) = self.history.pop() {
+                    self.board = snapshot.board;
+                    self.glues_left = snapshot.glues_left;
+                    self.cuts_left = snapshot.cuts_left;
+                    check_grammar = true;
+                }
+            }
+            if controls.clicked_down(Control::Restart) {
+                self.restart();
+                check_grammar = true;
+            }
+        }
+
+        if controls.clicked_down(Control::RightClick) || controls.clicked_down(Control::Cancel) {
+            match &self.selection {
+                SelectState::HoldingFragment { .. } => {
+                    self.cancel_hold();
+                    check_grammar = true;
+                }
+                SelectState::None if self.tool != Tool::Move => self.tool = Tool::Move,
+                SelectState::None => {
+                    if controls.clicked_down(Control::RightClick) {
+                        self.inspected =
+                            self.board.fragment_at(hovered_coord).map(|_| hovered_coord);
+                    } else {
+                        // Nothing to cancel, so they must want out
+                        return Transition::Push(Box::new(ModePause::new(
+                            self.level_id.clone(),
+                            self.restart_asked.clone(),
+                        )));
+                    }
+                }
+            }
+        }
+
         match &self.selection {
+            SelectState::None if self.tool != Tool::Move => {
+                if clicked {
+                    let (pos, dir) = self.hovered_edge;
+                    let snapshot = self.snapshot();
+                    let res = if self.tool == Tool::Glue {
+                        self.board.glue(pos, pos + dir)
+                    } else {
+                        self.board.cut(pos, dir)
+                    };
+                    if res.is_ok() {
+                        self.history.push(snapshot);
+                        let left = if self.tool == Tool::Glue {
+                            &mut self.glues_left
+                        } else {
+                            &mut self.cuts_left
+                        };
+                        *left -= 1;
+                        if *left == 0 {
+                            self.tool = Tool::Move;
+                        }
+
+                        check_grammar = true;
+                    }
+                }
+            }
             SelectState::None => {
-                if controls.clicked_down(Control::Click) {
+                if clicked {
                     let fragment_idx = self
                         .board
                         .fragments
@@ -116,6 +438,10 @@ impl Gamemode for ModePlaying {
                         .enumerate()
                         .find_map(|(idx, frag)| frag.contains(&hovered_coord).then_some(idx));
                     if let Some(frag_idx) = fragment_idx {
+                        self.inspected = None;
+                        let snapshot = self.snapshot();
+                        self.history.push(snapshot);
+
                         let frag_poses = self.board.fragments.remove(frag_idx);
                         let extracted = frag_poses
                             .into_iter()
@@ -133,19 +459,17 @@ impl Gamemode for ModePlaying {
                     }
                 }
             }
+            SelectState::HoldingFragment { origin, .. } if place && hovered_coord == *origin => {
+                // Putting it back where it was picked up isn't a move
+                self.cancel_hold();
+                check_grammar = true;
+            }
             SelectState::HoldingFragment { origin, symbols } => {
-                if controls.clicked_down(Control::Click) {
+                if place {
                     // Check if we can place it back
-                    const BOUNDS: IRect = IRect {
-                        left: 0,
-                        top: 0,
-                        width: SYMBOLS_ACROSS,
-                        height: SYMBOLS_DOWN,
-                    };
-
                     let collision_or_oob = symbols.iter().any(|(pos, _sym)| {
                         let newpos = *pos + hovered_coord - *origin;
-                        !BOUNDS.contains(newpos) || self.board.symbols.contains_key(&newpos)
+                        !self.bounds.contains(newpos) || self.board.symbols.contains_key(&newpos)
                     });
                     if !collision_or_oob {
                         // lovely!
@@ -170,6 +494,13 @@ impl Gamemode for ModePlaying {
                         }
 
                         check_grammar = true;
+                    } else if drag_to_place && dropped {
+                        if self.settings.reduced_motion {
+                            self.cancel_hold();
+                            check_grammar = true;
+                        } else {
+                            self.returning = Some((mouse, 0.0));
+                        }
                     }
                 }
             }
@@ -177,6 +508,10 @@ impl Gamemode for ModePlaying {
 
         if check_grammar {
             self.check_grammar();
+            // Only save between moves, not while something's picked up
+            if self.selection.is_none() {
+                self.save_progress();
+            }
         }
 
         Transition::None
@@ -187,35 +522,266 @@ impl Gamemode for ModePlaying {
             board: self.board.clone(),
             symbol_indices: self.symbol_indices.clone(),
             selection: self.selection.clone(),
+            hovered: self.board.symbols.get(&self.hovered_coord).cloned(),
+            inspected: self
+                .inspected
+                .and_then(|pos| self.board.fragment_at(pos))
+                .map(|idx| self.board.fragments[idx].clone()),
+            reveal: self.reveal,
             valid_poses: self.valid_poses.clone(),
+            explain: self.explain.then(|| self.grammar.clone()),
             won: self.won,
+            tool: self.tool,
+            glues_left: self.glues_left,
+            cuts_left: self.cuts_left,
+            hovered_edge: self.hovered_edge,
+            cursor: self.cursor,
+            held_at: self.held_at(),
+            bounds: self.bounds,
+            camera: self.camera,
+            palette: self.settings.theme.palette(),
+            load_error: self.load_error.clone(),
         })
     }
+
+    fn debug_info(&self) -> Vec<String> {
+        let pos = self.hovered_coord;
+        let mut out = vec![format!("hovering {}:{}", pos.x + 1, pos.y + 1)];
+        if let Some(sym) = self.board.symbols.get(&pos) {
+            out.push(format!("code {:025b}", sym.code));
+            out.push(format!("{:?}", sym.part_of_speech));
+        }
+        if let Some(idx) = self.board.fragment_at(pos) {
+            out.push(format!("fragment {}", idx));
+        }
+
+        let errors = &self.grammar.errors;
+        out.push(format!("{} grammar errors", errors.len()));
+        // Any more wouldn't fit
+        for error in errors.iter().take(4) {
+            out.push(format!("  {}", error));
+        }
+        if let Some(message) = &self.debug_message {
+            out.push(message.clone());
+        }
+        out
+    }
+
+    fn on_resume(&mut self, _assets: &LogicAssets) {
+        // They might have changed it in the settings
+        self.settings = Profile::current().settings.clone();
+
+        if self.restart_asked.swap(false, Ordering::Relaxed) {
+            self.restart();
+            self.check_grammar();
+            self.save_progress();
+        }
+    }
 }
 
 impl ModePlaying {
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            board: self.board.clone(),
+            glues_left: self.glues_left,
+            cuts_left: self.cuts_left,
+        }
+    }
+
+    /// Where to draw the held fragment's origin square, in pixels.
+    fn held_at(&self) -> Vec2 {
+        match (&self.returning, &self.selection, self.cursor) {
+            (Some((from, time)), SelectState::HoldingFragment { origin, .. }, _) => {
+                // ease out
+                let t = 1.0 - (1.0 - time / RETURN_TIME).powi(2);
+                *from + (self.camera.center(*origin) - *from) * t
+            }
+            // Follow the keyboard cursor if there is one, otherwise the mouse
+            (_, _, Some(cursor)) => self.camera.center(cursor),
+            _ => self.last_mouse,
+        }
+    }
+
+    /// Move the keyboard cursor around, starting it under the mouse if need be.
+    fn update_cursor(&mut self, controls: &InputSubscriber, mouse: Vec2) {
+        let start = self.cursor.unwrap_or_else(|| {
+            let under_mouse = self.camera.px_to_coord(mouse).unwrap_or(self.hovered_coord);
+            self.clamp_to_board(under_mouse)
+        });
+
+        for (ctrl, dir) in [
+            (Control::Up, Direction4::North),
+            (Control::Down, Direction4::South),
+            (Control::Left, Direction4::West),
+            (Control::Right, Direction4::East),
+        ] {
+            if controls.clicked_down(ctrl) {
+                self.cursor = Some(self.clamp_to_board(self.cursor.unwrap_or(start) + dir));
+                self.facing = dir;
+            }
+        }
+
+        if controls.clicked_down(Control::Select) && self.cursor.is_none() {
+            self.cursor = Some(start);
+        }
+
+        if controls.clicked_down(Control::NextFragment)
+            && self.selection.is_none()
+            && !self.board.fragments.is_empty()
+        {
+            if self.cursor.is_some() {
+                self.cycle_idx += 1;
+            }
+            self.cycle_idx %= self.board.fragments.len();
+            self.cursor = Some(self.board.fragments[self.cycle_idx][0]);
+        }
+    }
+
+    /// Put the held fragment back where it was picked up from.
+    ///
+    /// This doesn't count as a move, so the snapshot from picking it up is thrown away.
+    fn cancel_hold(&mut self) {
+        if let SelectState::HoldingFragment { symbols, .. } =
+            mem::replace(&mut self.selection, SelectState::None)
+        {
+            let fragment: Fragment = symbols.iter().map(|(pos, _)| *pos).collect();
+            self.board.fragments.push(fragment);
+            for (pos, sym) in symbols {
+                let clobber = self.board.symbols.insert(pos, sym);
+                assert_eq!(clobber, None);
+            }
+            self.history.pop();
+        }
+    }
+
+    /// Throw everything away and start the level over.
+    ///
+    /// This can't be undone, but it also wipes the move count and timer for a clean solve.
+    fn restart(&mut self) {
+        let Snapshot {
+            board,
+            glues_left,
+            cuts_left,
+        } = self.original.clone();
+        self.board = board;
+        self.glues_left = glues_left;
+        self.cuts_left = cuts_left;
+        self.history.clear();
+        self.moves_before = 0;
+        self.time_spent = 0.0;
+        self.hints_used = 0;
+        self.won = false;
+        self.tool = Tool::Move;
+        self.inspected = None;
+    }
+
+    /// The level file changed on disk, so start over with the new one.
+    /// If it didn't load, keep going with the old one and say why.
+    fn reloaded(&mut self, res: Result<(), String>, assets: &LogicAssets) {
+        match res {
+            Ok(()) => {
+                let idx = assets
+                    .levels()
+                    .iter()
+                    .position(|level| level.id == self.level_id);
+                if let Some(idx) = idx {
+                    *self = Self::new_from_level(idx, assets);
+                    // Show it how the file has it, not how it was left
+                    self.restart();
+                    self.check_grammar();
+                }
+            }
+            Err(problem) => self.load_error = Some(problem),
+        }
+    }
+
+    /// Write the board out as a level file,
+    /// to the clipboard if there is one and to a file if not.
+    fn dump_board(&mut self, assets: &LogicAssets) {
+        let res = assets
+            .levels()
+            .iter()
+            .find(|level| level.id == self.level_id)
+            .ok_or_else(|| anyhow::anyhow!("level {} isn't loaded", self.level_id))
+            .and_then(|level| level.dump(&self.board, self.glues_left, self.cuts_left));
+        self.debug_message = Some(match res {
+            Ok(toml) if clipboard::copy(&toml) => String::from("copied the board to the clipboard"),
+            Ok(toml) => {
+                let path = format!("{}.dump.toml", self.level_id);
+                match std::fs::write(&path, toml) {
+                    Ok(()) => format!("wrote the board to {}", path),
+                    Err(oh_no) => format!("couldn't write {}: {}", path, oh_no),
+                }
+            }
+            Err(oh_no) => {
+                warn!("Couldn't dump the board!\n{:?}", oh_no);
+                format!("couldn't dump the board: {}", oh_no)
+            }
+        });
+    }
+
+    /// Write down the board so the player can come back to it.
+    ///
+    /// Solved levels start fresh next time, so this has nothing to do once it's won.
+    fn save_progress(&self) {
+        if self.won {
+            return;
+        }
+        let mut profile = Profile::current();
+        profile
+            .levels
+            .entry(self.level_id.clone())
+            .or_default()
+            .in_progress = Some(InProgress {
+            board: self.board.save(),
+            glues_left: self.glues_left,
+            cuts_left: self.cuts_left,
+            moves: self.moves(),
+            time_spent: self.time_spent,
+            hints_used: self.hints_used,
+        });
+        profile.save();
+    }
+
+    /// Moves made on this level so far, not counting undone ones.
+    fn moves(&self) -> u32 {
+        self.moves_before + self.history.len() as u32
+    }
+
+    /// Move a position onto the nearest square on the board.
+    fn clamp_to_board(&self, pos: ICoord) -> ICoord {
+        let IRect {
+            left,
+            top,
+            width,
+            height,
+        } = self.bounds;
+        ICoord::new(
+            pos.x.clamp(left, left + width as isize - 1),
+            pos.y.clamp(top, top + height as isize - 1),
+        )
+    }
+
     fn check_grammar(&mut self) {
-        let (oks, errors) = self.board.check_grammar();
+        self.grammar = self.board.check_grammar();
         self.valid_poses.clear();
-        for ok in oks {
-            self.valid_poses.insert(ok);
+        for ok in self.grammar.okays.iter() {
+            self.valid_poses.insert(*ok);
         }
 
-        if self.selection.is_none() && errors.is_empty() {
+        if self.selection.is_none() && self.grammar.errors.is_empty() && !self.won {
             self.won = true;
+            // Don't count levels that start out solved
+            if self.moves() > 0 {
+                let mut profile = Profile::current();
+                let record = profile.levels.entry(self.level_id.clone()).or_default();
+                record.record_solve(self.moves(), self.time_spent, self.hints_used);
+                record.in_progress = None;
+                profile.save();
+            }
         }
     }
 }
 
-/// Given a coordinate in pixel space, and the center of the screen in world space,
-/// get the world space coordinates the pixel lies within.
-fn px_to_coord(pos: Vec2) -> ICoord {
-    let adjust = (pos - vec2(BOARD_ORIGIN_X, BOARD_ORIGIN_Y)) / SYMBOL_GAP - Vec2::splat(0.5);
-    ICoord::new(adjust.x.round() as isize, adjust.y.round() as isize)
-}
-
-/// Given a coordinate in world space and the center of the screen in world space,
-/// get the pixel coordinates of its center.
-fn coord_to_px(pos: ICoord) -> Vec2 {
-    (vec2(pos.x as f32, pos.y as f32) * SYMBOL_GAP).round() + vec2(BOARD_ORIGIN_X, BOARD_ORIGIN_Y)
-}
+mod script;
+mod test;
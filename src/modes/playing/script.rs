@@ -0,0 +1,256 @@
+#![cfg(test)]
+
+//! A little language for scripting gameplay in tests, and for checking levels' solutions.
+//!
+//! Each line is one step. Blank lines and lines starting with `#` are skipped.
+//! Squares are written `column:line`, counting from 1 like the errors for level files do.
+//!
+//! - `click X:Y` clicks the left mouse button on a square.
+//! - `right-click X:Y` clicks the right mouse button on a square.
+//! - `edge X:Y DIR` clicks by the `north`, `south`, `east` or `west` edge of a square,
+//!   for gluing and cutting.
+//! - `press KEY` taps a key, named like on the controls screen.
+//! - `wait N` lets N updates go by.
+//! - `expect won` and `expect unsolved` check whether the level's been beaten.
+//! - `expect moves N` checks how many moves have been made.
+//! - `expect board` checks the whole board against the lines after it that start with `|`.
+//!   They're drawn like the level file's board, with the same characters, starting right after the `|`.
+
+use std::str::FromStr;
+
+use ahash::AHashMap;
+use anyhow::{anyhow, bail, Context};
+use cogs_gamedev::grids::{Direction4, ICoord};
+use macroquad::prelude::{vec2, MouseButton, Vec2};
+
+use crate::{assets::LogicAssets, boilerplates::headless::Headless, controls::InputCode};
+
+use super::ModePlaying;
+
+#[derive(Debug, Clone)]
+enum Step {
+    Click(ICoord, MouseButton),
+    Edge(ICoord, Direction4),
+    Press(InputCode),
+    Wait(usize),
+    ExpectWon(bool),
+    ExpectMoves(u32),
+    /// What character should be at each position; everywhere else should be empty
+    ExpectBoard(AHashMap<ICoord, char>),
+}
+
+#[derive(Debug, Clone)]
+pub struct Script {
+    /// Each step, along with the line it's from
+    steps: Vec<(usize, Step)>,
+}
+
+impl Script {
+    pub fn parse(src: &str) -> anyhow::Result<Script> {
+        let mut steps = Vec::new();
+        let mut lines = src.lines().enumerate().peekable();
+        while let Some((idx, line)) = lines.next() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let step = if line == "expect board" {
+                let mut layout = AHashMap::new();
+                let mut y = 0;
+                while let Some((_, row)) = lines.next_if(|(_, row)| row.trim().starts_with('|')) {
+                    let row = &row.trim_start()[1..];
+                    for (x, c) in row.chars().enumerate() {
+                        if !(c.is_whitespace() || c == '.' || c == '_') {
+                            layout.insert(ICoord::new(x as isize, y), c);
+                        }
+                    }
+                    y += 1;
+                }
+                Step::ExpectBoard(layout)
+            } else {
+                parse_step(line).with_context(|| format!("on line {}", idx + 1))?
+            };
+            steps.push((idx + 1, step));
+        }
+
+        Ok(Script { steps })
+    }
+
+    /// Play the level with this ID, following the script.
+    ///
+    /// Returns the game afterwards, to look at more closely.
+    pub fn run(&self, level_id: &str) -> anyhow::Result<Headless> {
+        let assets = LogicAssets::init_blocking()?;
+        let level_idx = assets
+            .levels()
+            .iter()
+            .position(|level| level.id == level_id)
+            .ok_or_else(|| anyhow!("there's no level {}", level_id))?;
+        let mut game = Headless::with_assets(assets, |assets| {
+            Box::new(ModePlaying::new_from_level(level_idx, assets))
+        });
+        // Settle in
+        game.run(2);
+        game.settle();
+
+        for (line, step) in &self.steps {
+            run_step(&mut game, level_idx, step).with_context(|| format!("on line {}", line))?;
+        }
+        Ok(game)
+    }
+}
+
+fn parse_step(line: &str) -> anyhow::Result<Step> {
+    let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let rest = rest.trim();
+    Ok(match command {
+        "click" => Step::Click(parse_square(rest)?, MouseButton::Left),
+        "right-click" => Step::Click(parse_square(rest)?, MouseButton::Right),
+        "edge" => {
+            let (square, dir) = rest
+                .split_once(' ')
+                .ok_or_else(|| anyhow!("edge needs a square and a direction"))?;
+            let dir = match dir.trim() {
+                "north" => Direction4::North,
+                "south" => Direction4::South,
+                "east" => Direction4::East,
+                "west" => Direction4::West,
+                other => bail!("`{}` isn't a direction", other),
+            };
+            Step::Edge(parse_square(square)?, dir)
+        }
+        "press" => Step::Press(InputCode::from_str(rest)?),
+        "wait" => Step::Wait(rest.parse()?),
+        "expect" => match rest.split_once(' ').unwrap_or((rest, "")) {
+            ("won", "") => Step::ExpectWon(true),
+            ("unsolved", "") => Step::ExpectWon(false),
+            ("moves", count) => Step::ExpectMoves(count.trim().parse()?),
+            _ => bail!("don't know how to expect `{}`", rest),
+        },
+        _ => bail!("unknown command `{}`", command),
+    })
+}
+
+/// Read a `column:line` square, counting from 1.
+fn parse_square(s: &str) -> anyhow::Result<ICoord> {
+    let (x, y) = s
+        .split_once(':')
+        .ok_or_else(|| anyhow!("`{}` isn't a square like 3:4", s))?;
+    let x: isize = x.trim().parse()?;
+    let y: isize = y.trim().parse()?;
+    Ok(ICoord::new(x - 1, y - 1))
+}
+
+/// Write a square the way scripts do.
+fn show_square(pos: ICoord) -> String {
+    format!("{}:{}", pos.x + 1, pos.y + 1)
+}
+
+fn playing(game: &Headless) -> anyhow::Result<&ModePlaying> {
+    game.top()
+        .ok_or_else(|| anyhow!("the level isn't on top of the stack"))
+}
+
+fn playing_mut(game: &mut Headless) -> anyhow::Result<&mut ModePlaying> {
+    game.top_mut()
+        .ok_or_else(|| anyhow!("the level isn't on top of the stack"))
+}
+
+fn run_step(game: &mut Headless, level_idx: usize, step: &Step) -> anyhow::Result<()> {
+    match step {
+        Step::Click(pos, button) => {
+            let playing = playing_mut(game)?;
+            playing.camera.show(*pos, playing.bounds);
+            let px = playing.camera.center(*pos);
+            click_at(game, px, *button);
+        }
+        Step::Edge(pos, dir) => {
+            let playing = playing_mut(game)?;
+            playing.camera.show(*pos, playing.bounds);
+            // Just inside the edge, measured from the middle of the square and its gutter
+            let gap = playing.camera.gap();
+            let deltas = dir.deltas();
+            let px = playing.camera.coord_to_px(*pos)
+                + Vec2::splat(gap / 2.0)
+                + vec2(deltas.x as f32, deltas.y as f32) * (gap / 2.0 - 2.0);
+            click_at(game, px, MouseButton::Left);
+        }
+        Step::Press(input) => {
+            game.controls.press(*input);
+            game.update();
+            game.controls.release(*input);
+            game.update();
+            // Let whatever it opened or closed finish sliding in
+            game.settle();
+        }
+        Step::Wait(updates) => game.run(*updates),
+        Step::ExpectWon(won) => {
+            let playing = playing(game)?;
+            if playing.won != *won {
+                bail!(
+                    "expected the level to be {}, but it wasn't; problems are {:?}",
+                    if *won { "won" } else { "unsolved" },
+                    playing.grammar.errors
+                );
+            }
+        }
+        Step::ExpectMoves(moves) => {
+            let actual = playing(game)?.moves();
+            if actual != *moves {
+                bail!("expected {} moves, but there were {}", moves, actual);
+            }
+        }
+        Step::ExpectBoard(layout) => {
+            let levels = game.assets.levels();
+            let legend = &levels[level_idx].legend;
+            let name_of = |code: u32| {
+                legend
+                    .iter()
+                    .find_map(|(c, sym)| (sym.code == code).then_some(*c))
+                    .unwrap_or('?')
+            };
+            let expected = layout
+                .iter()
+                .map(|(pos, c)| match legend.get(c) {
+                    Some(sym) => Ok((*pos, sym.code)),
+                    None => Err(anyhow!("`{}` isn't in the level's symbols", c)),
+                })
+                .collect::<anyhow::Result<AHashMap<_, _>>>()?;
+
+            let board = &playing(game)?.board;
+            let mut wrong = Vec::new();
+            for pos in expected.keys().chain(board.symbols.keys()) {
+                let want = expected.get(pos).copied();
+                let got = board.symbols.get(pos).map(|sym| sym.code);
+                if want != got {
+                    let describe = |code: Option<u32>| {
+                        code.map_or_else(|| String::from("nothing"), |code| name_of(code).into())
+                    };
+                    wrong.push(format!(
+                        "at {} wanted {}, found {}",
+                        show_square(*pos),
+                        describe(want),
+                        describe(got)
+                    ));
+                }
+            }
+            if !wrong.is_empty() {
+                wrong.sort();
+                wrong.dedup();
+                bail!("the board is wrong:\n{}", wrong.join("\n"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Move the mouse somewhere, then click there.
+fn click_at(game: &mut Headless, px: Vec2, button: MouseButton) {
+    game.controls.set_mouse((px.x, px.y));
+    game.update();
+    game.controls.press(InputCode::Mouse(button));
+    game.update();
+    game.controls.release(InputCode::Mouse(button));
+    game.update();
+}
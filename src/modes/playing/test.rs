@@ -0,0 +1,240 @@
+#![cfg(test)]
+
+use cogs_gamedev::grids::{ICoord, IRect};
+use macroquad::prelude::{vec2, KeyCode, MouseButton, Vec2};
+
+use crate::{
+    assets::LogicAssets,
+    boilerplates::headless::Headless,
+    controls::InputCode,
+    modes::{ModePause, ModePlaying, ModeSettings},
+    utils::{profile::Profile, settings::InputMode},
+};
+
+use super::{
+    camera::{Camera, MAX_ZOOM},
+    script::Script,
+    SelectState,
+};
+
+const BOUNDS: IRect = IRect {
+    left: 0,
+    top: 0,
+    width: 40,
+    height: 30,
+};
+
+#[test]
+fn hit_testing_at_every_zoom() {
+    let mut camera = Camera::default();
+    camera.pan(vec2(23.0, 41.0), BOUNDS);
+    for _ in 1..=MAX_ZOOM {
+        let size = camera.display_size();
+        for pos in camera.visible(BOUNDS) {
+            let corner = camera.coord_to_px(pos);
+            // The whole square, gutter included, should land on it
+            for offset in [
+                Vec2::ZERO,
+                Vec2::splat(size - 1.0),
+                Vec2::splat(camera.gap() - 0.01),
+            ] {
+                let px = corner + offset;
+                if Camera::view().contains(px) {
+                    assert_eq!(camera.px_to_coord(px), Some(pos), "{:?} at {:?}", pos, px);
+                }
+            }
+        }
+        camera.zoom_by(1, Camera::view_center(), BOUNDS);
+    }
+    assert_eq!(camera.px_to_coord(vec2(0.0, 0.0)), None);
+}
+
+#[test]
+fn zoom_keeps_anchor_still() {
+    let mut camera = Camera::default();
+    camera.pan(vec2(100.0, 100.0), BOUNDS);
+    let anchor = Camera::view_center();
+    let under = camera.px_to_coord(anchor);
+    camera.zoom_by(1, anchor, BOUNDS);
+    assert_eq!(camera.px_to_coord(anchor), under);
+    camera.zoom_by(-1, anchor, BOUNDS);
+    assert_eq!(camera.px_to_coord(anchor), under);
+
+    // Zoomed all the way out, the corner square is still on screen
+    camera.zoom_by(-5, anchor, BOUNDS);
+    camera.show(ICoord::new(0, 0), BOUNDS);
+    assert_eq!(
+        camera.px_to_coord(Camera::view().point()),
+        Some(ICoord::new(0, 0))
+    );
+}
+
+/// Click the left mouse button on a square.
+fn click_on(game: &mut Headless, pos: ICoord) {
+    let px = game.expect_top::<ModePlaying>().camera.center(pos);
+    game.controls.set_mouse((px.x, px.y));
+    game.controls.press(InputCode::Mouse(MouseButton::Left));
+    game.update();
+    game.controls.release(InputCode::Mouse(MouseButton::Left));
+    game.update();
+}
+
+fn tap_key(game: &mut Headless, key: KeyCode) {
+    game.controls.press(InputCode::Key(key));
+    game.update();
+    game.controls.release(InputCode::Key(key));
+    game.update();
+    game.settle();
+}
+
+#[test]
+fn move_and_undo_headless() {
+    let mut game = Headless::new(|assets| Box::new(ModePlaying::new_from_level(0, assets)));
+    game.run(5);
+
+    click_on(&mut game, ICoord::new(1, 1));
+    let playing = game.expect_top::<ModePlaying>();
+    assert!(matches!(
+        playing.selection,
+        SelectState::HoldingFragment { .. }
+    ));
+    assert!(!playing.board.symbols.contains_key(&ICoord::new(1, 1)));
+
+    click_on(&mut game, ICoord::new(8, 8));
+    let playing = game.expect_top::<ModePlaying>();
+    assert!(playing.selection.is_none());
+    for x in 8..=10 {
+        assert!(playing.board.symbols.contains_key(&ICoord::new(x, 8)));
+    }
+    assert_eq!(playing.moves(), 1);
+
+    tap_key(&mut game, KeyCode::Z);
+    let playing = game.expect_top::<ModePlaying>();
+    assert!(playing.board.symbols.contains_key(&ICoord::new(1, 1)));
+    assert!(!playing.board.symbols.contains_key(&ICoord::new(8, 8)));
+    assert_eq!(playing.moves(), 0);
+
+    // The settings go on top and come back off
+    tap_key(&mut game, KeyCode::F1);
+    assert_eq!(game.depth(), 2);
+    game.expect_top::<ModeSettings>();
+    tap_key(&mut game, KeyCode::Escape);
+    assert_eq!(game.depth(), 1);
+    game.expect_top::<ModePlaying>();
+}
+
+#[test]
+fn dropping_in_place_isnt_a_move() {
+    Profile::current().settings.input_mode = InputMode::DragAndDrop;
+    let mut game = Headless::new(|assets| Box::new(ModePlaying::new_from_level(0, assets)));
+    game.run(5);
+
+    // Pressing and letting go without moving puts it right back
+    click_on(&mut game, ICoord::new(1, 1));
+    let playing = game.expect_top::<ModePlaying>();
+    assert!(playing.selection.is_none());
+    assert!(playing.board.symbols.contains_key(&ICoord::new(1, 1)));
+    assert_eq!(playing.moves(), 0);
+
+    // Actually dragging it somewhere does count
+    let (from, to) = {
+        let camera = game.expect_top::<ModePlaying>().camera;
+        (
+            camera.center(ICoord::new(1, 1)),
+            camera.center(ICoord::new(8, 8)),
+        )
+    };
+    game.controls.set_mouse((from.x, from.y));
+    game.controls.press(InputCode::Mouse(MouseButton::Left));
+    game.update();
+    game.controls.set_mouse((to.x, to.y));
+    game.update();
+    game.controls.release(InputCode::Mouse(MouseButton::Left));
+    game.update();
+    let playing = game.expect_top::<ModePlaying>();
+    assert!(playing.board.symbols.contains_key(&ICoord::new(8, 8)));
+    assert_eq!(playing.moves(), 1);
+}
+
+#[test]
+fn pause_and_restart() {
+    let mut game = Headless::new(|assets| Box::new(ModePlaying::new_from_level(0, assets)));
+    game.run(5);
+    click_on(&mut game, ICoord::new(1, 1));
+    click_on(&mut game, ICoord::new(8, 8));
+    assert_eq!(game.expect_top::<ModePlaying>().moves(), 1);
+    // Out of the way, so it doesn't pick anything on the menu
+    game.controls.set_mouse((0.0, 0.0));
+
+    // Escape with nothing held pauses, and again resumes
+    tap_key(&mut game, KeyCode::Escape);
+    assert_eq!(game.depth(), 2);
+    game.expect_top::<ModePause>();
+    tap_key(&mut game, KeyCode::Escape);
+    assert_eq!(game.expect_top::<ModePlaying>().moves(), 1);
+
+    tap_key(&mut game, KeyCode::Escape);
+    tap_key(&mut game, KeyCode::Down);
+    tap_key(&mut game, KeyCode::Enter);
+    assert_eq!(game.depth(), 1);
+    let playing = game.expect_top::<ModePlaying>();
+    assert_eq!(playing.moves(), 0);
+    assert!(playing.board.symbols.contains_key(&ICoord::new(1, 1)));
+}
+
+#[test]
+fn solve_shipped_levels() {
+    let assets = LogicAssets::init_blocking().unwrap();
+    for level in assets.levels().iter() {
+        let solution = level
+            .solution
+            .as_ref()
+            .unwrap_or_else(|| panic!("{} doesn't have a solution", level.id));
+        let game = Script::parse(solution)
+            .and_then(|script| script.run(&level.id))
+            .unwrap_or_else(|oh_no| panic!("couldn't play {}'s solution: {:?}", level.id, oh_no));
+        assert!(
+            game.expect_top::<ModePlaying>().won,
+            "{}'s solution doesn't solve it",
+            level.id
+        );
+    }
+}
+
+#[test]
+fn scripted_mistakes() {
+    let script = Script::parse(
+        r#"
+        # Putting it down on top of something doesn't work
+        click 7:6
+        click 3:6
+        expect board
+        |
+        | @AB
+        |
+        | @CD
+        |
+        |  EF
+        expect moves 1
+
+        # Putting it back where it was doesn't count
+        press Escape
+        expect moves 0
+        expect unsolved
+
+        click 7:6
+        click 2:6
+        expect won
+        expect board
+        |
+        | @AB
+        |
+        | @CD
+        |
+        | @EF
+        expect moves 1
+        "#,
+    )
+    .unwrap();
+    script.run("test1").unwrap();
+}
@@ -0,0 +1,295 @@
+use cogs_gamedev::controls::InputHandler;
+use macroquad::prelude::{KeyCode, Vec2};
+
+use crate::{
+    assets::{LogicAssets, RenderAssets},
+    boilerplates::{Effect, FrameInfo, Gamemode, GamemodeDrawer, Transition},
+    controls::{Control, InputCode, InputSubscriber},
+    modes::ModePlaying,
+    utils::{
+        draw::hexcolor,
+        menu::Menu,
+        profile::{Profile, Slots, MAX_NAME_LEN},
+        text::{self, Markup},
+    },
+    HEIGHT, WIDTH,
+};
+
+/// Screen for picking who's playing, shown after the logo.
+#[derive(Clone)]
+pub struct ModeProfiles {
+    menu: Menu,
+    state: ProfilesState,
+    /// What went wrong last, to tell the player about
+    message: Option<String>,
+}
+
+#[derive(Clone)]
+enum ProfilesState {
+    /// Picking a profile to play as, or something to do to them
+    Browsing,
+    /// Picking a profile to rename or delete
+    Choosing(Action),
+    /// Typing in a name
+    Naming {
+        /// The slot being renamed, or `None` for a new one
+        renaming: Option<u32>,
+        name: String,
+    },
+    ConfirmDelete(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    Rename,
+    Delete,
+}
+
+impl ModeProfiles {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        let mut out = Self {
+            menu: Menu::new(Vec::new(), Vec2::ZERO, 0.0),
+            state: ProfilesState::Browsing,
+            message: None,
+        };
+        out.browse();
+        // Start on whoever played last
+        let slots = Slots::current();
+        out.menu.selected = slots
+            .slots
+            .iter()
+            .position(|slot| slot.id == slots.active)
+            .unwrap_or(0);
+        out
+    }
+
+    fn menu(entries: Vec<String>) -> Menu {
+        Menu::new(entries, Vec2::new(16.0, 32.0), WIDTH - 32.0).with_rows(16)
+    }
+
+    fn slot_names() -> Vec<String> {
+        Slots::current()
+            .slots
+            .iter()
+            .map(|slot| slot.name.clone())
+            .collect()
+    }
+
+    fn browse(&mut self) {
+        let mut entries = Self::slot_names();
+        entries.extend(
+            ["new profile", "rename a profile", "delete a profile"]
+                .iter()
+                .map(|s| s.to_string()),
+        );
+        self.menu = Self::menu(entries);
+        self.state = ProfilesState::Browsing;
+    }
+
+    fn choose(&mut self, action: Action) {
+        let mut entries = Self::slot_names();
+        entries.push(String::from("never mind"));
+        self.menu = Self::menu(entries);
+        self.state = ProfilesState::Choosing(action);
+    }
+
+    /// Start playing as this slot.
+    fn play(&mut self, id: u32, controls: &InputSubscriber, assets: &LogicAssets) -> Transition {
+        Profile::switch_to(id);
+        let mut profile = Profile::current();
+        profile.open_count += 1;
+        profile.save();
+        controls.rebind(InputSubscriber::with_overrides(&profile.controls));
+        drop(profile);
+
+        Transition::Swap(Box::new(ModePlaying::new_from_level(0, assets)))
+            .animated(Effect::Dissolve, 0.8)
+    }
+
+    fn update_naming(&mut self, controls: &InputSubscriber) {
+        let (renaming, name) = match &mut self.state {
+            ProfilesState::Naming { renaming, name } => (*renaming, name),
+            _ => return,
+        };
+
+        // Read the keys raw, because space and letters are bound to things
+        match controls.just_pressed() {
+            Some(InputCode::Key(KeyCode::Escape)) => {
+                self.browse();
+                return;
+            }
+            Some(InputCode::Key(KeyCode::Backspace)) => {
+                name.pop();
+            }
+            Some(InputCode::Key(KeyCode::Enter)) | Some(InputCode::Key(KeyCode::KpEnter)) => {
+                let res = match renaming {
+                    Some(id) => Slots::current().rename(id, name),
+                    None => Slots::current().create(name).map(|_| ()),
+                };
+                match res {
+                    Ok(()) => {
+                        self.message = None;
+                        self.browse();
+                    }
+                    Err(oh_no) => self.message = Some(oh_no.to_string()),
+                }
+                return;
+            }
+            _ => {}
+        }
+
+        // The font only has ASCII
+        for c in controls.typed().chars() {
+            if (c.is_ascii_graphic() || c == ' ') && name.chars().count() < MAX_NAME_LEN {
+                name.push(c);
+            }
+        }
+    }
+}
+
+impl Gamemode for ModeProfiles {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        _frame_info: FrameInfo,
+        assets: &LogicAssets,
+    ) -> Transition {
+        if let ProfilesState::Naming { .. } = self.state {
+            self.update_naming(controls);
+            return Transition::None;
+        }
+
+        let picked = self.menu.update(controls);
+        let slots = Slots::current().slots.clone();
+        match self.state.clone() {
+            ProfilesState::Browsing => match picked {
+                Some(idx) if idx < slots.len() => {
+                    return self.play(slots[idx].id, controls, assets);
+                }
+                Some(idx) if idx == slots.len() => {
+                    self.state = ProfilesState::Naming {
+                        renaming: None,
+                        name: String::new(),
+                    };
+                }
+                Some(idx) if idx == slots.len() + 1 => self.choose(Action::Rename),
+                Some(_) => self.choose(Action::Delete),
+                None => {}
+            },
+            ProfilesState::Choosing(action) => {
+                if controls.clicked_down(Control::Cancel) {
+                    self.browse();
+                    return Transition::None;
+                }
+                match picked {
+                    Some(idx) if idx < slots.len() => {
+                        let slot = &slots[idx];
+                        if action == Action::Rename {
+                            self.state = ProfilesState::Naming {
+                                renaming: Some(slot.id),
+                                name: slot.name.clone(),
+                            };
+                        } else {
+                            self.menu = Self::menu(vec![
+                                format!("delete {} forever", slot.name),
+                                String::from("keep it"),
+                            ]);
+                            // Default to the safe one
+                            self.menu.selected = 1;
+                            self.state = ProfilesState::ConfirmDelete(slot.id);
+                        }
+                    }
+                    Some(_) => self.browse(),
+                    None => {}
+                }
+            }
+            ProfilesState::ConfirmDelete(id) => {
+                if controls.clicked_down(Control::Cancel) || picked == Some(1) {
+                    self.browse();
+                } else if picked == Some(0) {
+                    let res = Slots::current().delete(id);
+                    match res {
+                        Ok(()) => {
+                            // Don't keep the deleted one loaded
+                            if Profile::current().slot == id {
+                                let active = Slots::current().active;
+                                Profile::switch_to(active);
+                            }
+                            self.message = None;
+                        }
+                        Err(oh_no) => self.message = Some(oh_no.to_string()),
+                    }
+                    self.browse();
+                }
+            }
+            ProfilesState::Naming { .. } => unreachable!(),
+        }
+
+        Transition::None
+    }
+
+    fn get_draw_info(&mut self) -> Box<dyn GamemodeDrawer> {
+        // I am my own drawer
+        Box::new(self.clone())
+    }
+}
+
+impl GamemodeDrawer for ModeProfiles {
+    fn draw(&self, assets: &RenderAssets, frame_info: FrameInfo) {
+        use macroquad::prelude::*;
+
+        clear_background(hexcolor(0x21181bff));
+
+        let fonts = &assets.textures.fonts;
+        text::draw_wrapped(
+            "who's playing?",
+            vec2(16.0, 8.0),
+            WIDTH,
+            Markup::plain(fonts.medium, WHITE),
+        );
+
+        let prompt = match &self.state {
+            ProfilesState::Browsing => "pick your profile",
+            ProfilesState::Choosing(Action::Rename) => "rename which profile?",
+            ProfilesState::Choosing(Action::Delete) => "delete which profile?",
+            ProfilesState::Naming { renaming: None, .. } => {
+                "type a name for the new profile, then press enter"
+            }
+            ProfilesState::Naming { .. } => "type a new name, then press enter",
+            ProfilesState::ConfirmDelete(_) => "this can't be undone!",
+        };
+        text::draw_wrapped(
+            prompt,
+            vec2(16.0, 20.0),
+            WIDTH - 32.0,
+            Markup::plain(fonts.small, WHITE),
+        );
+
+        if let ProfilesState::Naming { name, .. } = &self.state {
+            // Blink the cursor
+            let cursor = if (frame_info.frames_ran / 15).is_multiple_of(2) {
+                "_"
+            } else {
+                ""
+            };
+            text::draw_wrapped(
+                &format!("> {}{}", name, cursor),
+                vec2(16.0, 32.0),
+                WIDTH - 32.0,
+                Markup::plain(fonts.small, hexcolor(0xfbf236ff)),
+            );
+        } else {
+            self.menu.draw_default(fonts.small);
+        }
+
+        if let Some(message) = &self.message {
+            text::draw_wrapped(
+                message,
+                vec2(16.0, HEIGHT - 12.0),
+                WIDTH - 32.0,
+                Markup::plain(fonts.small, hexcolor(0xac3232ff)),
+            );
+        }
+    }
+}
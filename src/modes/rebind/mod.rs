@@ -0,0 +1,235 @@
+use ahash::AHashMap;
+use cogs_gamedev::controls::InputHandler;
+use itertools::Itertools;
+use macroquad::prelude::{KeyCode, Vec2};
+
+use crate::{
+    assets::{LogicAssets, RenderAssets},
+    boilerplates::{FrameInfo, Gamemode, GamemodeDrawer, Transition},
+    controls::{Control, InputCode, InputSubscriber},
+    utils::{
+        draw::hexcolor,
+        menu::Menu,
+        profile::Profile,
+        text::{self, Markup},
+    },
+    HEIGHT, WIDTH,
+};
+
+/// Screen for rebinding controls.
+///
+/// Every change is saved to the profile and swapped into the `InputSubscriber` right away.
+#[derive(Clone)]
+pub struct ModeRebind {
+    menu: Menu,
+    /// The bindings as edited so far.
+    bindings: AHashMap<InputCode, Control>,
+    state: RebindState,
+    /// Something to tell the player instead of the usual prompt
+    note: Option<String>,
+}
+
+#[derive(Clone)]
+enum RebindState {
+    /// Picking something off the menu
+    Browsing,
+    /// Waiting for the player to press an input to add to or remove from this
+    Waiting(Control),
+    /// The player pressed an input that's already bound to something else.
+    /// Pressing it again moves it over to this control.
+    Conflict {
+        control: Control,
+        input: InputCode,
+        other: Control,
+    },
+}
+
+impl ModeRebind {
+    pub fn new(controls: &InputSubscriber) -> Self {
+        let bindings = controls.bindings().clone();
+        let menu = Menu::new(
+            Self::entries(&bindings),
+            Vec2::new(16.0, 20.0),
+            WIDTH - 32.0,
+        )
+        .with_rows(20);
+        Self {
+            menu,
+            bindings,
+            state: RebindState::Browsing,
+            note: None,
+        }
+    }
+
+    fn entries(bindings: &AHashMap<InputCode, Control>) -> Vec<String> {
+        Control::ALL
+            .iter()
+            .map(|ctrl| {
+                let inputs = bindings
+                    .iter()
+                    .filter(|(_, bound)| *bound == ctrl)
+                    .map(|(input, _)| input.to_string())
+                    .sorted()
+                    .join(", ");
+                if inputs.is_empty() {
+                    format!("{}: (none)", ctrl.name())
+                } else {
+                    format!("{}: {}", ctrl.name(), inputs)
+                }
+            })
+            .chain([String::from("reset to defaults"), String::from("back")])
+            .collect()
+    }
+
+    /// Save the bindings and start using them.
+    fn apply(&mut self, controls: &InputSubscriber) {
+        controls.rebind(self.bindings.clone());
+
+        let mut profile = Profile::current();
+        profile.controls = InputSubscriber::overrides(&self.bindings);
+        profile.save();
+
+        self.menu.entries = Self::entries(&self.bindings);
+    }
+
+    /// Does this control only have one input left?
+    fn only_input(&self, control: Control) -> bool {
+        self.bindings
+            .values()
+            .filter(|bound| **bound == control)
+            .count()
+            <= 1
+    }
+
+    /// Handle an input pressed while waiting to bind something.
+    ///
+    /// New inputs get added to the control, and ones it already has get taken off it.
+    /// Every other input stays how it was, so binding a key doesn't lose the mouse or gamepad.
+    fn try_bind(&mut self, control: Control, input: InputCode, controls: &InputSubscriber) {
+        match self.bindings.get(&input) {
+            // Leaving a control with nothing would be a good way to get stuck
+            Some(&bound) if self.only_input(bound) => {
+                self.note = Some(format!(
+                    "{} is all {} has left, so it stays",
+                    input,
+                    bound.name()
+                ));
+            }
+            Some(&other) if other != control => {
+                self.state = RebindState::Conflict {
+                    control,
+                    input,
+                    other,
+                };
+                return;
+            }
+            Some(_) => {
+                self.bindings.remove(&input);
+                self.note = Some(format!("took {} off {}", input, control.name()));
+            }
+            None => {
+                self.bindings.insert(input, control);
+                self.note = Some(format!("added {} to {}", input, control.name()));
+            }
+        }
+        self.apply(controls);
+        self.state = RebindState::Browsing;
+    }
+}
+
+impl Gamemode for ModeRebind {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        _frame_info: FrameInfo,
+        _assets: &LogicAssets,
+    ) -> Transition {
+        // Escape always backs out of binding, so you can't get stuck
+        let escape = controls.just_pressed() == Some(InputCode::Key(KeyCode::Escape));
+
+        match self.state.clone() {
+            RebindState::Browsing => {
+                if controls.clicked_down(Control::Cancel) {
+                    return Transition::Pop;
+                }
+                match self.menu.update(controls) {
+                    Some(idx) if idx < Control::ALL.len() => {
+                        self.state = RebindState::Waiting(Control::ALL[idx]);
+                        self.note = None;
+                    }
+                    Some(idx) if idx == Control::ALL.len() => {
+                        self.bindings = InputSubscriber::default_controls();
+                        self.apply(controls);
+                        self.note = Some(String::from("back to the defaults"));
+                    }
+                    Some(_) => return Transition::Pop,
+                    None => {}
+                }
+            }
+            RebindState::Waiting(control) => match controls.just_pressed() {
+                _ if escape => self.state = RebindState::Browsing,
+                Some(input) => self.try_bind(control, input, controls),
+                None => {}
+            },
+            RebindState::Conflict { control, input, .. } => match controls.just_pressed() {
+                _ if escape => self.state = RebindState::Browsing,
+                Some(again) if again == input => {
+                    self.bindings.insert(input, control);
+                    self.note = Some(format!("moved {} to {}", input, control.name()));
+                    self.apply(controls);
+                    self.state = RebindState::Browsing;
+                }
+                Some(different) => self.try_bind(control, different, controls),
+                None => {}
+            },
+        }
+
+        Transition::None
+    }
+
+    fn get_draw_info(&mut self) -> Box<dyn GamemodeDrawer> {
+        // I am my own drawer
+        Box::new(self.clone())
+    }
+}
+
+impl GamemodeDrawer for ModeRebind {
+    fn draw(&self, assets: &RenderAssets, _frame_info: FrameInfo) {
+        use macroquad::prelude::*;
+
+        clear_background(hexcolor(0x21181bff));
+
+        let fonts = &assets.textures.fonts;
+        text::draw_wrapped(
+            "controls",
+            vec2(16.0, 8.0),
+            WIDTH,
+            Markup::plain(fonts.medium, WHITE),
+        );
+        self.menu.draw_default(fonts.small);
+
+        let prompt = match &self.state {
+            RebindState::Browsing => match &self.note {
+                Some(note) => note.clone(),
+                None => String::from("pick a control to add or remove its inputs"),
+            },
+            RebindState::Waiting(control) => format!(
+                "add/remove an input for {} (escape to cancel)",
+                control.name()
+            ),
+            RebindState::Conflict { input, other, .. } => format!(
+                "{} is already used for {}. press it again to move it",
+                input,
+                other.name()
+            ),
+        };
+        text::draw_wrapped(
+            &prompt,
+            vec2(16.0, HEIGHT - 12.0),
+            WIDTH - 32.0,
+            Markup::plain(fonts.small, hexcolor(0x92e8c0ff)),
+        );
+    }
+}
+
+mod test;
@@ -0,0 +1,46 @@
+#![cfg(test)]
+
+use macroquad::prelude::{KeyCode, MouseButton};
+
+use crate::controls::{Control, InputCode, InputSubscriber};
+
+use super::{ModeRebind, RebindState};
+
+#[test]
+fn rebinding_keeps_other_inputs() {
+    let controls = InputSubscriber::headless(InputSubscriber::default_controls());
+    let mut rebind = ModeRebind::new(&controls);
+    let bound = |rebind: &ModeRebind, input| rebind.bindings.get(&input).copied();
+
+    // Adding a key to click leaves the mouse alone
+    rebind.try_bind(Control::Click, InputCode::Key(KeyCode::K), &controls);
+    assert_eq!(
+        bound(&rebind, InputCode::Key(KeyCode::K)),
+        Some(Control::Click)
+    );
+    assert_eq!(
+        bound(&rebind, InputCode::Mouse(MouseButton::Left)),
+        Some(Control::Click)
+    );
+
+    // Pressing one select already has takes just that one off
+    rebind.try_bind(Control::Select, InputCode::Key(KeyCode::Space), &controls);
+    assert_eq!(bound(&rebind, InputCode::Key(KeyCode::Space)), None);
+    assert_eq!(
+        bound(&rebind, InputCode::Key(KeyCode::Enter)),
+        Some(Control::Select)
+    );
+
+    // Taken from another control only when it's pressed twice
+    rebind.try_bind(Control::Glue, InputCode::Key(KeyCode::K), &controls);
+    assert!(matches!(rebind.state, RebindState::Conflict { .. }));
+    assert_eq!(
+        bound(&rebind, InputCode::Key(KeyCode::K)),
+        Some(Control::Click)
+    );
+
+    // Nothing gets left with no inputs at all
+    let backslash = InputCode::Key(KeyCode::Backslash);
+    rebind.try_bind(Control::Debug, backslash, &controls);
+    assert_eq!(bound(&rebind, backslash), Some(Control::Debug));
+}
@@ -0,0 +1,282 @@
+use cogs_gamedev::controls::InputHandler;
+use macroquad::prelude::{warn, Vec2};
+
+use crate::{
+    assets::{LogicAssets, RenderAssets},
+    boilerplates::{Effect, FrameInfo, Gamemode, GamemodeDrawer, Transition},
+    controls::{Control, InputSubscriber},
+    modes::ModeRebind,
+    utils::{
+        clipboard,
+        draw::hexcolor,
+        menu::Menu,
+        profile::Profile,
+        settings::{Settings, Theme, MAX_VOLUME},
+        text::{self, Markup},
+    },
+    HEIGHT, WIDTH,
+};
+
+/// How many entries at the top of the menu are options that get changed in place.
+const OPTION_COUNT: usize = 8;
+/// Entries after the options.
+const ENTRIES: &[&str] = &["controls", "export profile", "import profile", "back"];
+
+/// Screen for everything that isn't playing the game.
+#[derive(Clone)]
+pub struct ModeSettings {
+    menu: Menu,
+    state: SettingsState,
+    /// What happened last, to tell the player about
+    message: Option<String>,
+}
+
+#[derive(Clone)]
+enum SettingsState {
+    Browsing,
+    /// The player pasted a profile, and we're checking they really want it
+    ConfirmImport {
+        profile: Box<Profile>,
+        /// What would change, one line each
+        changes: Vec<String>,
+    },
+}
+
+impl ModeSettings {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            menu: Self::browsing_menu(),
+            state: SettingsState::Browsing,
+            message: None,
+        }
+    }
+
+    fn browsing_menu() -> Menu {
+        let mut entries = Self::option_entries(&Profile::current().settings);
+        entries.extend(ENTRIES.iter().map(|s| s.to_string()));
+        Menu::new(entries, Vec2::new(16.0, 20.0), WIDTH - 32.0)
+    }
+
+    fn option_entries(settings: &Settings) -> Vec<String> {
+        let on_off = |it: bool| if it { "on" } else { "off" };
+        let entries = vec![
+            format!("volume: {}/{}", settings.master_volume, MAX_VOLUME),
+            format!("music: {}/{}", settings.music_volume, MAX_VOLUME),
+            format!("sound effects: {}/{}", settings.sfx_volume, MAX_VOLUME),
+            format!("fullscreen: {}", on_off(settings.fullscreen)),
+            format!(
+                "pixel scaling: {}",
+                if settings.integer_scale {
+                    "whole numbers"
+                } else {
+                    "fill the window"
+                }
+            ),
+            format!("colors: {}", settings.theme.name()),
+            format!("reduced motion: {}", on_off(settings.reduced_motion)),
+            format!("placing: {}", settings.input_mode.name()),
+        ];
+        debug_assert_eq!(entries.len(), OPTION_COUNT);
+        entries
+    }
+
+    /// Change the option at this index one step forwards or backwards.
+    ///
+    /// Volumes stop at the ends when going by steps of one, and wrap around otherwise,
+    /// so picking them over and over still gets everywhere.
+    fn adjust(settings: &mut Settings, idx: usize, step: i32, wrap: bool) {
+        let volume = |vol: &mut u8| {
+            let next = *vol as i32 + step;
+            *vol = if wrap {
+                next.rem_euclid(MAX_VOLUME as i32 + 1) as u8
+            } else {
+                next.clamp(0, MAX_VOLUME as i32) as u8
+            };
+        };
+        match idx {
+            0 => volume(&mut settings.master_volume),
+            1 => volume(&mut settings.music_volume),
+            2 => volume(&mut settings.sfx_volume),
+            3 => settings.fullscreen = !settings.fullscreen,
+            4 => settings.integer_scale = !settings.integer_scale,
+            5 => {
+                let themes = Theme::ALL;
+                let now = themes
+                    .iter()
+                    .position(|theme| *theme == settings.theme)
+                    .unwrap_or(0) as i32;
+                let next = (now + step).rem_euclid(themes.len() as i32);
+                settings.theme = themes[next as usize];
+            }
+            6 => settings.reduced_motion = !settings.reduced_motion,
+            7 => settings.input_mode = settings.input_mode.next(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Change an option and save it straight away.
+    fn change_option(&mut self, idx: usize, step: i32, wrap: bool) {
+        let mut profile = Profile::current();
+        Self::adjust(&mut profile.settings, idx, step, wrap);
+        profile.settings.apply();
+        profile.save();
+
+        let entries = Self::option_entries(&profile.settings);
+        self.menu.entries[..OPTION_COUNT].clone_from_slice(&entries);
+    }
+
+    fn export(&mut self) {
+        let res = Profile::current().export();
+        self.message = Some(match res {
+            Ok(code) if clipboard::copy(&code) => {
+                String::from("copied your profile to the clipboard. keep it somewhere safe!")
+            }
+            Ok(_) => String::from("there's no clipboard in this build. try --export-profile"),
+            Err(oh_no) => {
+                warn!("Couldn't export profile!\n{:?}", oh_no);
+                String::from("couldn't export your profile, sorry")
+            }
+        });
+    }
+
+    fn import(&mut self) {
+        let code = match clipboard::paste() {
+            Some(it) => it,
+            None => {
+                self.message = Some(String::from(
+                    "couldn't read the clipboard. copy a profile code first",
+                ));
+                return;
+            }
+        };
+        match Profile::import(&code) {
+            Ok(profile) => {
+                let changes = Profile::current().describe_changes(&profile);
+                let mut menu = Menu::new(
+                    vec![
+                        String::from("replace my profile"),
+                        String::from("keep my profile"),
+                    ],
+                    Vec2::new(16.0, 40.0 + changes.len() as f32 * 10.0),
+                    WIDTH - 32.0,
+                );
+                // Default to the safe one
+                menu.selected = 1;
+                self.menu = menu;
+                self.state = SettingsState::ConfirmImport {
+                    profile: Box::new(profile),
+                    changes,
+                };
+                self.message = None;
+            }
+            Err(oh_no) => {
+                warn!("Couldn't import profile!\n{:?}", oh_no);
+                self.message = Some(format!("couldn't import that: {}", oh_no));
+            }
+        }
+    }
+
+    fn back_to_browsing(&mut self) {
+        self.menu = Self::browsing_menu();
+        self.state = SettingsState::Browsing;
+    }
+}
+
+impl Gamemode for ModeSettings {
+    fn update(
+        &mut self,
+        controls: &InputSubscriber,
+        _frame_info: FrameInfo,
+        _assets: &LogicAssets,
+    ) -> Transition {
+        let picked = self.menu.update(controls);
+        match &self.state {
+            SettingsState::Browsing => {
+                if controls.clicked_down(Control::Cancel) {
+                    return Transition::Pop.animated(Effect::Wipe, 0.3);
+                }
+                let selected = self.menu.selected;
+                if selected < OPTION_COUNT {
+                    if controls.clicked_down(Control::Left) {
+                        self.change_option(selected, -1, false);
+                    } else if controls.clicked_down(Control::Right) {
+                        self.change_option(selected, 1, false);
+                    }
+                }
+                match picked {
+                    Some(idx) if idx < OPTION_COUNT => self.change_option(idx, 1, true),
+                    Some(idx) => match idx - OPTION_COUNT {
+                        0 => return Transition::Push(Box::new(ModeRebind::new(controls))),
+                        1 => self.export(),
+                        2 => self.import(),
+                        _ => return Transition::Pop.animated(Effect::Wipe, 0.3),
+                    },
+                    None => {}
+                }
+            }
+            SettingsState::ConfirmImport { profile, .. } => {
+                if controls.clicked_down(Control::Cancel) || picked == Some(1) {
+                    self.back_to_browsing();
+                } else if picked == Some(0) {
+                    let profile = (**profile).clone();
+                    controls.rebind(InputSubscriber::with_overrides(&profile.controls));
+                    Profile::current().replace(profile);
+
+                    self.back_to_browsing();
+                    self.message = Some(String::from("imported!"));
+                }
+            }
+        }
+
+        Transition::None
+    }
+
+    fn get_draw_info(&mut self) -> Box<dyn GamemodeDrawer> {
+        // I am my own drawer
+        Box::new(self.clone())
+    }
+}
+
+impl GamemodeDrawer for ModeSettings {
+    fn draw(&self, assets: &RenderAssets, _frame_info: FrameInfo) {
+        use macroquad::prelude::*;
+
+        clear_background(hexcolor(0x21181bff));
+
+        let fonts = &assets.textures.fonts;
+        text::draw_wrapped(
+            "settings",
+            vec2(16.0, 8.0),
+            WIDTH,
+            Markup::plain(fonts.medium, WHITE),
+        );
+
+        if let SettingsState::ConfirmImport { changes, .. } = &self.state {
+            text::draw_wrapped(
+                "importing would change:",
+                vec2(16.0, 20.0),
+                WIDTH - 32.0,
+                Markup::plain(fonts.small, WHITE),
+            );
+            for (idx, change) in changes.iter().enumerate() {
+                text::draw_wrapped(
+                    change,
+                    vec2(24.0, 31.0 + idx as f32 * 10.0),
+                    WIDTH - 40.0,
+                    Markup::plain(fonts.small, hexcolor(0xfbf236ff)),
+                );
+            }
+        }
+        self.menu.draw_default(fonts.small);
+
+        if let Some(message) = &self.message {
+            text::draw_wrapped(
+                message,
+                vec2(16.0, HEIGHT - 12.0),
+                WIDTH - 32.0,
+                Markup::plain(fonts.small, hexcolor(0x92e8c0ff)),
+            );
+        }
+    }
+}
@@ -1,10 +1,14 @@
 use ahash::{AHashMap, AHashSet};
-use anyhow::anyhow;
-use cogs_gamedev::grids::ICoord;
+use anyhow::{anyhow, bail};
+use cogs_gamedev::grids::{Direction4, ICoord};
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 
-use super::{parse, symbols::Symbol};
+use super::{
+    parse::{self, GrammarError, Sentence},
+    symbols::Symbol,
+};
 
 /// The playfield the player moves symbols around.
 ///
@@ -15,21 +19,41 @@ pub struct Board {
     pub symbols: AHashMap<ICoord, Symbol>,
     /// Symbols that are held together in fragments.
     /// Each entry in the Vec is a list of coordinates that are stuck together.
-    pub fragments: Vec<SmallVec<[ICoord; 8]>>,
+    pub fragments: Vec<Fragment>,
+}
+
+/// A list of coordinates that are stuck together.
+pub type Fragment = SmallVec<[ICoord; 8]>;
+
+/// A board the player has been working on, in a form that can go in the profile.
+///
+/// Symbols are only stored by their code; `Board::load` looks the rest up on the level.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedBoard {
+    symbols: Vec<((isize, isize), u32)>,
+    fragments: Vec<Vec<(isize, isize)>>,
+}
+
+/// Everything we found out checking the grammar of a board.
+#[derive(Debug, Clone, Default)]
+pub struct GrammarReport {
+    /// Every sentence we tried to parse, one per start sigil, sorted top-to-bottom.
+    pub sentences: Vec<Sentence>,
+    /// The coordinates of all the grammatically correct sentences.
+    pub okays: Vec<ICoord>,
+    /// All the encountered errors.
+    /// If it's empty then everything was OK!
+    pub errors: Vec<GrammarError>,
 }
 
 impl Board {
     /// Test this board for all the symbols in grammatically correct sentences and all the symbols
     /// outside of them.
     ///
-    /// The first vec has the coordinates of all the grammatically correct sentences (in batches).
-    /// The second vec has all the encountered errors.
-    /// If it's empty then everything was OK!
-    ///
     /// All the positions *not* in the grammatically correct group are problematic.
     /// If there are any free-floating symbols detail about that will be pushed to the errors vec.
-    pub fn check_grammar(&self) -> (Vec<ICoord>, Vec<anyhow::Error>) {
-        let (okays, mut errors): (Vec<_>, Vec<_>) = self
+    pub fn check_grammar(&self) -> GrammarReport {
+        let sentences = self
             .symbols
             .iter()
             .filter_map(|(pos, symbol)| {
@@ -40,29 +64,210 @@ impl Board {
                     None
                 }
             })
+            .sorted_by_key(|pos| (pos.y, pos.x))
             .map(|start| parse::check_from_start(&self.symbols, start))
-            .partition_result();
+            .collect_vec();
 
         let mut okays_set = AHashSet::new();
-        let okays = okays
-            .into_iter()
-            .flatten()
-            // i swear i'm getting more unhinged as i write this
-            // inspect? really??
-            .inspect(|pos| {
-                okays_set.insert(*pos);
-            })
-            .collect_vec();
+        let mut okays = Vec::new();
+        let mut errors = Vec::new();
+        for sentence in sentences.iter() {
+            match &sentence.result {
+                Ok(poses) => {
+                    for pos in poses {
+                        okays_set.insert(*pos);
+                        okays.push(*pos);
+                    }
+                }
+                Err(oh_no) => errors.push(oh_no.clone()),
+            }
+        }
 
         let remaining = self
             .symbols
             .keys()
             .filter(|pos| !okays_set.contains(*pos))
-            .collect_vec();
-        if !remaining.is_empty() {
-            errors.push(anyhow!("there were leftover symbols at: {:?}", remaining));
+            .count();
+        if remaining == 1 {
+            errors.push(GrammarError::new(
+                None,
+                String::from("1 symbol isn't in a sentence"),
+            ));
+        } else if remaining > 1 {
+            errors.push(GrammarError::new(
+                None,
+                format!("{} symbols aren't in a sentence", remaining),
+            ));
+        }
+
+        GrammarReport {
+            sentences,
+            okays,
+            errors,
+        }
+    }
+}
+
+impl Board {
+    /// Get the index of the fragment covering this position.
+    pub fn fragment_at(&self, pos: ICoord) -> Option<usize> {
+        self.fragments.iter().position(|frag| frag.contains(&pos))
+    }
+
+    /// Stick the fragments at two touching positions together into one fragment.
+    pub fn glue(&mut self, a: ICoord, b: ICoord) -> anyhow::Result<()> {
+        if !a.neighbors4().contains(&b) {
+            bail!("{} and {} aren't touching", a, b);
+        }
+        let a_idx = self
+            .fragment_at(a)
+            .ok_or_else(|| anyhow!("no fragment at {}", a))?;
+        let b_idx = self
+            .fragment_at(b)
+            .ok_or_else(|| anyhow!("no fragment at {}", b))?;
+        if a_idx == b_idx {
+            bail!("{} and {} are already in the same fragment", a, b);
+        }
+
+        // Remove the later one first so the earlier index stays good
+        let (keep, remove) = (a_idx.min(b_idx), a_idx.max(b_idx));
+        let removed = self.fragments.remove(remove);
+        self.fragments[keep].extend(removed);
+        Ok(())
+    }
+
+    /// Split the fragment at `pos` along the grid line between `pos` and its neighbor in `dir`.
+    ///
+    /// The line runs all the way across the fragment, and any bits that end up disconnected
+    /// become their own fragments.
+    pub fn cut(&mut self, pos: ICoord, dir: Direction4) -> anyhow::Result<()> {
+        let other = pos + dir;
+        let idx = self
+            .fragment_at(pos)
+            .ok_or_else(|| anyhow!("no fragment at {}", pos))?;
+        if !self.fragments[idx].contains(&other) {
+            bail!("{} and {} aren't in the same fragment", pos, other);
         }
 
-        (okays, errors)
+        let deltas = dir.deltas();
+        // Project everything onto the cut direction; the line is between pos and other.
+        let line = pos.x * deltas.x + pos.y * deltas.y;
+        let (near, far): (AHashSet<_>, AHashSet<_>) = self
+            .fragments
+            .remove(idx)
+            .into_iter()
+            .partition(|cell| cell.x * deltas.x + cell.y * deltas.y <= line);
+
+        self.fragments.extend(flood_fragments(&near));
+        self.fragments.extend(flood_fragments(&far));
+        Ok(())
+    }
+}
+
+impl Board {
+    pub fn save(&self) -> SavedBoard {
+        SavedBoard {
+            symbols: self
+                .symbols
+                .iter()
+                .map(|(pos, sym)| ((pos.x, pos.y), sym.code))
+                .collect(),
+            fragments: self
+                .fragments
+                .iter()
+                .map(|frag| frag.iter().map(|pos| (pos.x, pos.y)).collect())
+                .collect(),
+        }
     }
+
+    /// Put a saved board back together, using the symbols from `original`.
+    ///
+    /// The level file might have changed since it was saved, so this makes sure it has
+    /// exactly the same symbols as `original` and that the fragments still make sense.
+    pub fn load(saved: &SavedBoard, original: &Board) -> anyhow::Result<Board> {
+        let known: AHashMap<u32, &Symbol> = original
+            .symbols
+            .values()
+            .map(|sym| (sym.code, sym))
+            .collect();
+
+        let mut symbols = AHashMap::new();
+        for &((x, y), code) in saved.symbols.iter() {
+            let pos = ICoord::new(x, y);
+            let sym = known
+                .get(&code)
+                .ok_or_else(|| anyhow!("symbol {:#x} isn't in the level", code))?;
+            if symbols.insert(pos, (*sym).clone()).is_some() {
+                bail!("two symbols at {}", pos);
+            }
+        }
+        let counts = |syms: &AHashMap<ICoord, Symbol>| syms.values().map(|sym| sym.code).counts();
+        if counts(&symbols) != counts(&original.symbols) {
+            bail!("the level has different symbols than the saved board");
+        }
+
+        let mut covered = AHashSet::new();
+        let mut fragments = Vec::new();
+        for frag in saved.fragments.iter() {
+            let fragment: Fragment = frag.iter().map(|&(x, y)| ICoord::new(x, y)).collect();
+            if fragment.is_empty() {
+                bail!("there's an empty fragment");
+            }
+            for pos in fragment.iter() {
+                if !symbols.contains_key(pos) {
+                    bail!("fragment covers {}, which has no symbol", pos);
+                }
+                if !covered.insert(*pos) {
+                    bail!("{} is in two fragments", pos);
+                }
+            }
+            let cells = fragment.iter().copied().collect();
+            if flood_fragments(&cells).len() != 1 {
+                bail!("the fragment at {} is in pieces", fragment[0]);
+            }
+            fragments.push(fragment);
+        }
+        if covered.len() != symbols.len() {
+            bail!("some symbols aren't in a fragment");
+        }
+
+        Ok(Board { symbols, fragments })
+    }
+}
+
+/// Group a bunch of positions into fragments of positions that touch.
+pub fn flood_fragments(cells: &AHashSet<ICoord>) -> Vec<Fragment> {
+    let mut flooded_to = AHashSet::new();
+    let mut fragments = Vec::new();
+    // reuse memory
+    let mut working_on = Vec::new();
+
+    for pos in cells.iter() {
+        if !flooded_to.contains(pos) {
+            // i've never met this pos in my life
+            let mut fragment = SmallVec::new();
+            working_on.push(*pos);
+
+            while let Some(pos) = working_on.pop() {
+                if flooded_to.insert(pos) {
+                    fragment.push(pos);
+
+                    for present_new_neighbor in pos
+                        .neighbors4()
+                        .iter()
+                        .filter(|&nbor| cells.contains(nbor) && !flooded_to.contains(nbor))
+                    {
+                        working_on.push(*present_new_neighbor);
+                    }
+                }
+            }
+
+            // Just in case
+            working_on.clear();
+
+            fragments.push(fragment);
+        }
+    }
+
+    fragments
 }
@@ -1,12 +1,17 @@
-use std::str::FromStr;
+use std::{collections::BTreeMap, fmt::Write, str::FromStr};
 
-use ahash::{AHashMap, AHashSet};
+use ahash::AHashMap;
 use anyhow::{anyhow, bail, Context};
 use cogs_gamedev::grids::{Coord, ICoord};
 use serde::Deserialize;
-use smallvec::SmallVec;
 
-use super::{board::Board, symbols::Symbol};
+use super::{
+    board::{flood_fragments, Board},
+    symbols::{Symbol, SYMBOL_SIZE},
+};
+
+/// Boards are at least this big on each side, which is exactly what fits on screen.
+pub const MIN_BOARD_SIZE: usize = 13;
 
 /// Level as directly serialized from a file.
 #[derive(Debug, Deserialize)]
@@ -16,6 +21,20 @@ pub struct RawLevel {
     /// and then arrange the characters into the wanted shapes.
     symbols: AHashMap<char, String>,
     board: String,
+    #[serde(default)]
+    reveal: RevealedRules,
+    /// How many times the player can glue two fragments together
+    #[serde(default)]
+    glues: u32,
+    /// How many times the player can cut a fragment apart
+    #[serde(default)]
+    cuts: u32,
+    /// How big the board is.
+    /// By default it's just big enough for the layout, and at least `MIN_BOARD_SIZE`.
+    width: Option<usize>,
+    height: Option<usize>,
+    /// Steps that solve the level, in the language of the scripted tests
+    solution: Option<String>,
 }
 
 impl RawLevel {
@@ -59,37 +78,30 @@ impl RawLevel {
             })
             .collect::<Result<_, _>>()?;
 
-        // Flood fill everything together
-        let mut flooded_to = AHashSet::new();
-        let mut fragments = Vec::new();
-        // reuse memory
-        let mut working_on = Vec::new();
-
-        for pos in laid_out.keys() {
-            if !flooded_to.contains(pos) {
-                // i've never met this pos in my life
-                let mut fragment = SmallVec::new();
-                working_on.push(*pos);
-
-                while let Some(pos) = working_on.pop() {
-                    if flooded_to.insert(pos) {
-                        fragment.push(pos);
-
-                        for present_new_neighbor in pos.neighbors4().iter().filter(|&nbor| {
-                            laid_out.contains_key(nbor) && !flooded_to.contains(nbor)
-                        }) {
-                            working_on.push(*present_new_neighbor);
-                        }
-                    }
-                }
-
-                // Just in case
-                working_on.clear();
-
-                fragments.push(fragment);
-            }
+        let fit =
+            |extent: Option<isize>| (extent.map_or(0, |e| e as usize + 1)).max(MIN_BOARD_SIZE);
+        let width = self
+            .width
+            .unwrap_or_else(|| fit(laid_out.keys().map(|pos| pos.x).max()));
+        let height = self
+            .height
+            .unwrap_or_else(|| fit(laid_out.keys().map(|pos| pos.y).max()));
+        if let Some(pos) = laid_out
+            .keys()
+            .find(|pos| pos.x as usize >= width || pos.y as usize >= height)
+        {
+            bail!(
+                "the symbol at {}:{} is outside the {}x{} board",
+                pos.x + 1,
+                pos.y + 1,
+                width,
+                height
+            );
         }
 
+        // Flood fill everything together
+        let fragments = flood_fragments(&laid_out.keys().copied().collect());
+
         // Render the layout into symbols
         let symbols = laid_out
             .into_iter()
@@ -105,6 +117,13 @@ impl RawLevel {
             id: filename,
             name: self.name.clone(),
             original_board: board,
+            legend: char_symbols,
+            solution: self.solution.clone(),
+            reveal: self.reveal,
+            glues: self.glues,
+            cuts: self.cuts,
+            width,
+            height,
         })
     }
 }
@@ -116,4 +135,127 @@ pub struct Level {
     pub name: String,
     /// Original board state
     pub original_board: Board,
+    /// Which character in the level file stands for which symbol
+    pub legend: AHashMap<char, Symbol>,
+    /// Steps that solve the level, for the tests to check it really can be solved.
+    /// See `modes::playing::script`.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub solution: Option<String>,
+    /// What the symbol inspector is allowed to tell the player
+    pub reveal: RevealedRules,
+    /// How many glue actions the player gets
+    pub glues: u32,
+    /// How many cut actions the player gets
+    pub cuts: u32,
+    /// Size of the board in squares
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Level {
+    /// Write out a level file like this level, but with this board and this many glues and cuts.
+    ///
+    /// Level files don't say where fragments split, since they get flood-filled when loading,
+    /// so anything glued or cut won't come back quite the same.
+    pub fn dump(&self, board: &Board, glues: u32, cuts: u32) -> anyhow::Result<String> {
+        // Keep the same characters as the level file where we can
+        let mut chars: AHashMap<u32, char> =
+            self.legend.iter().map(|(c, sym)| (sym.code, *c)).collect();
+        let mut spare = ('A'..='Z')
+            .chain('a'..='z')
+            .chain('0'..='9')
+            .filter(|c| !self.legend.contains_key(c));
+
+        let mut rows = vec![vec![' '; self.width]; self.height];
+        // Sorted so the file comes out the same every time
+        let mut symbols = BTreeMap::new();
+        for (pos, sym) in board.symbols.iter() {
+            let c = match chars.get(&sym.code) {
+                Some(c) => *c,
+                None => {
+                    let c = spare
+                        .next()
+                        .ok_or_else(|| anyhow!("ran out of characters for symbols"))?;
+                    chars.insert(sym.code, c);
+                    c
+                }
+            };
+            *rows
+                .get_mut(pos.y as usize)
+                .and_then(|row| row.get_mut(pos.x as usize))
+                .ok_or_else(|| anyhow!("{} is off the board", pos))? = c;
+            symbols.insert(c, sym.code);
+        }
+
+        let board = rows
+            .iter()
+            .map(|row| row.iter().collect::<String>().trim_end().to_owned())
+            .collect::<Vec<_>>()
+            .join("\n");
+        if board.contains("'''") {
+            bail!("the board can't be written with `'''` in it");
+        }
+
+        // Literal strings, so nothing needs escaping
+        let mut out = String::new();
+        writeln!(
+            out,
+            "# Dumped from {} by the debug overlay.\n\
+             # Fragments get flood-filled when this loads, so glues and cuts don't carry over.",
+            self.id
+        )?;
+        writeln!(out, "name = {:?}", self.name)?;
+        writeln!(out, "glues = {}", glues)?;
+        writeln!(out, "cuts = {}", cuts)?;
+        writeln!(out, "width = {}", self.width)?;
+        writeln!(out, "height = {}", self.height)?;
+        writeln!(out, "board = '''\n{}\n'''", board)?;
+
+        writeln!(out, "\n[symbols]")?;
+        for (c, code) in symbols {
+            let pattern = (0..SYMBOL_SIZE)
+                .map(|y| {
+                    (0..SYMBOL_SIZE)
+                        .map(|x| {
+                            if code & (1 << (SYMBOL_SIZE * y + x)) != 0 {
+                                '#'
+                            } else {
+                                ' '
+                            }
+                        })
+                        .collect::<String>()
+                        .trim_end()
+                        .to_owned()
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+            writeln!(out, "{:?} = '''\n{}'''", c.to_string(), pattern)?;
+        }
+
+        let RevealedRules {
+            part_of_speech,
+            islands,
+            depth,
+        } = self.reveal;
+        writeln!(out, "\n[reveal]")?;
+        writeln!(out, "part_of_speech = {}", part_of_speech)?;
+        writeln!(out, "islands = {}", islands)?;
+        writeln!(out, "depth = {}", depth)?;
+
+        Ok(out)
+    }
+}
+
+/// Which of the hidden rules the symbol inspector shows on a level.
+///
+/// Everything is hidden by default so early levels don't give the game away.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct RevealedRules {
+    /// Show whether a symbol is a particle, noun or verb.
+    pub part_of_speech: bool,
+    /// Show how many islands a noun or verb has.
+    pub islands: bool,
+    /// Show how deep a modifier is.
+    pub depth: bool,
 }
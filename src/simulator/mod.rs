@@ -1,6 +1,6 @@
 pub mod board;
 pub mod levels;
-mod parse;
+pub mod parse;
 pub mod symbols;
 
 mod test;
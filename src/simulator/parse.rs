@@ -1,94 +1,159 @@
-use std::convert::{TryFrom, TryInto};
+use std::{convert::TryFrom, fmt};
 
 use ahash::AHashMap;
-use anyhow::{anyhow, bail};
 use cogs_gamedev::grids::{Direction4, ICoord, Rotation};
 use enum_map::{enum_map, Enum, EnumMap};
-use itertools::{FoldWhile, Itertools};
+use itertools::Itertools;
 use once_cell::sync::Lazy;
 use smallvec::{smallvec, SmallVec};
 
 use super::symbols::{PartOfSpeech, Symbol};
 
-/// Check if a sequence of blocks is grammatically valid, starting from the start symbol
+/// A sentence found on the board, starting from a start sigil, whether or not it parsed.
+#[derive(Debug, Clone)]
+pub struct Sentence {
+    /// Where the start sigil is.
+    pub origin: ICoord,
+    /// The direction the spine reads in, if we got far enough to know it.
+    pub dir: Option<Direction4>,
+    /// Every position on the spine that parsed OK, starting with the start sigil.
+    pub spine: Vec<ICoord>,
+    /// `Ok` with all the coordinates in the sentence, or `Err` with where and why parsing stopped.
+    pub result: Result<Vec<ICoord>, GrammarError>,
+}
+
+/// Why something isn't grammatical, phrased so it can be shown to the player.
+#[derive(Debug, Clone)]
+pub struct GrammarError {
+    /// The exact position where parsing failed, if there is one.
+    pub pos: Option<ICoord>,
+    /// Short explanation, like "adjective has 2 islands, noun has 1".
+    pub reason: String,
+}
+
+impl GrammarError {
+    pub fn new(pos: Option<ICoord>, reason: String) -> Self {
+        Self { pos, reason }
+    }
+}
+
+impl fmt::Display for GrammarError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.pos {
+            Some(pos) => write!(f, "{} (at {})", self.reason, pos),
+            None => write!(f, "{}", self.reason),
+        }
+    }
+}
+
+impl std::error::Error for GrammarError {}
+
+/// Like `bail!` but for `GrammarError`s at a position.
+macro_rules! fail {
+    ($pos:expr, $($arg:tt)*) => {
+        return Err(GrammarError::new(Some($pos), format!($($arg)*)))
+    };
+}
+
+/// Check if a sequence of blocks is grammatically valid, starting from the start symbol.
 ///
-/// If it is, returns `Ok` with a list of all the coordinates in the sentence.
-/// If not, returns `Err`.
-pub fn check_from_start(
+/// The returned sentence's `result` is `Ok` with a list of all the coordinates in the sentence
+/// if it's valid.
+pub fn check_from_start(symbols: &AHashMap<ICoord, Symbol>, origin: ICoord) -> Sentence {
+    let mut sentence = Sentence {
+        origin,
+        dir: None,
+        spine: Vec::new(),
+        result: Ok(Vec::new()),
+    };
+    sentence.result = parse_sentence(symbols, &mut sentence);
+    sentence
+}
+
+/// Fill in the sentence's direction and spine as we go, and return what its result should be.
+fn parse_sentence(
     symbols: &AHashMap<ICoord, Symbol>,
-    origin: ICoord,
-) -> anyhow::Result<Vec<ICoord>> {
+    sentence: &mut Sentence,
+) -> Result<Vec<ICoord>, GrammarError> {
+    let origin = sentence.origin;
+
     // I type this code so much i should just put a `neighbors4` method on ICoord...
-    let dir = *Direction4::DIRECTIONS
+    let neighbors = Direction4::DIRECTIONS
         .iter()
-        .filter(|dir| {
-            let neighbor = origin + **dir;
-            symbols.get(&neighbor).is_some()
-        })
-        .exactly_one()
-        .map_err(|oh_no| {
-            anyhow!(
-                "wanted exactly 1 occupied neighbor but got {:?}",
-                oh_no.collect_vec()
-            )
-        })?;
+        .copied()
+        .filter(|dir| symbols.get(&(origin + *dir)).is_some())
+        .collect_vec();
+    let dir = match neighbors.as_slice() {
+        [dir] => *dir,
+        [] => fail!(origin, "start has no neighbors"),
+        many => fail!(origin, "start has {} neighbors", many.len()),
+    };
+    sentence.dir = Some(dir);
 
     // We can pretty easily parse this with a state machine.
     // Each state has a mapping of symbol types to what the next state is.
     let mut state = SpineState::Origin;
     let mut idx = 0;
-    let spine_len = loop {
+    loop {
         let pos = origin + dir.deltas() * idx;
         let sym = symbols.get(&pos);
 
-        let posk: PartOfSpeechKind = sym.map(|sym| &sym.part_of_speech).try_into()?;
+        let posk = match PartOfSpeechKind::try_from(sym.map(|sym| &sym.part_of_speech)) {
+            Ok(it) => it,
+            Err(reason) => fail!(pos, "{}", reason),
+        };
         // Deliberate annotation cause rust-analyzer doesn't like it
         let next_states: &SmallVec<[(PartOfSpeechKind, SpineState); 2]> = &SPINE_STATES[state];
         let next = next_states
             .iter()
-            .find_map(|(target, next)| (&posk == target).then_some(*next))
-            .ok_or_else(|| anyhow!("{:?} does not allow {:?}", &state, &posk));
+            .find_map(|(target, next)| (&posk == target).then_some(*next));
 
         match next {
-            Ok(SpineState::Satisfied) => {
+            Some(SpineState::Satisfied) => {
                 // we're done here! nice
-                break idx;
+                break;
             }
-            Ok(next) => state = next,
-            Err(oh_no) => return Err(oh_no),
+            Some(next) => state = next,
+            None => fail!(
+                pos,
+                "wanted {}, got {}",
+                next_states.iter().map(|(kind, _)| kind.name()).join(" or "),
+                posk.name()
+            ),
         }
 
+        sentence.spine.push(pos);
         idx += 1;
-    };
+    }
 
     // add modifier positions to this as they happen.
-    // we do .. and not ..= because we always end with an EOF => no symbol.
-    let mut seen_poses = (0..spine_len)
-        .map(|idx| origin + dir.deltas() * idx)
-        .collect_vec();
+    // the spine never has the EOF in it because that's not a symbol.
+    let mut seen_poses = sentence.spine.clone();
 
     // Now, for each noun/verb on the spine, check it for modifiers.
     let adj_dir = dir.rotate(Rotation::Clockwise);
     let adv_dir = dir.rotate(Rotation::CounterClockwise);
-    for spine_pos in seen_poses.clone() {
-        let base_sym = symbols.get(&spine_pos).ok_or_else(|| {
-            anyhow!(
-                "somehow previously had symbol on the spine at {} and now don't",
-                spine_pos
-            )
-        })?;
+    for &spine_pos in sentence.spine.iter() {
+        let base_sym = match symbols.get(&spine_pos) {
+            Some(it) => it,
+            None => fail!(
+                spine_pos,
+                "somehow previously had symbol on the spine and now don't"
+            ),
+        };
         let moddable = match base_sym.part_of_speech {
             PartOfSpeech::Noun { islands, depth: 0 } => Some((islands, true)),
             PartOfSpeech::Verb { islands, depth: 0 } => Some((islands, false)),
-            PartOfSpeech::Noun { .. } | PartOfSpeech::Verb { .. } => bail!(
-                "somehow had a modifier {:?} on the spine at {} when we should have checked for it",
-                base_sym,
-                spine_pos
+            PartOfSpeech::Noun { .. } | PartOfSpeech::Verb { .. } => fail!(
+                spine_pos,
+                "somehow had a modifier {:?} on the spine when we should have checked for it",
+                base_sym
             ),
             _ => None,
         };
         if let Some((islands, is_noun)) = moddable {
             // Check for modifiers down and modifier-modifiers up
+            let base_name = noun_or_verb(is_noun);
 
             'each_mod: for (is_adj, look_dir) in [(true, adj_dir), (false, adv_dir)] {
                 // start counting at 1
@@ -110,25 +175,26 @@ pub fn check_from_start(
                             };
                             let error_name = if is_adj { "adjective" } else { "adverb" };
                             if is_noun != mod_is_noun {
-                                bail!(
-                                    "the {} at {}, {:?} was {} a noun but the base was {} a noun",
-                                    error_name,
+                                fail!(
                                     mod_pos,
-                                    sym,
-                                    mod_is_noun,
-                                    is_noun
+                                    "{} is a {} but it's on a {}",
+                                    error_name,
+                                    noun_or_verb(mod_is_noun),
+                                    base_name
                                 );
                             }
                             if mod_depth != 1 {
-                                bail!(
-                                    "the {} at {}, {:?}, had a bad depth",
-                                    error_name,
-                                    mod_pos,
-                                    sym
-                                );
+                                fail!(mod_pos, "{} has depth {}, wanted 1", error_name, mod_depth);
                             }
                             if mod_islands != islands {
-                                bail!("the {} at {}, {:?}, did not have the right island count (wanted {})", error_name, mod_pos, sym, islands);
+                                fail!(
+                                    mod_pos,
+                                    "{} has {}, {} has {}",
+                                    error_name,
+                                    island_count(mod_islands),
+                                    base_name,
+                                    islands
+                                );
                             }
 
                             // This seems to be valid!
@@ -147,6 +213,23 @@ pub fn check_from_start(
     Ok(seen_poses)
 }
 
+fn noun_or_verb(is_noun: bool) -> &'static str {
+    if is_noun {
+        "noun"
+    } else {
+        "verb"
+    }
+}
+
+/// "1 island" or "N islands"
+fn island_count(islands: u8) -> String {
+    if islands == 1 {
+        String::from("1 island")
+    } else {
+        format!("{} islands", islands)
+    }
+}
+
 /// Parts of speech on the spine of a sentence.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum PartOfSpeechKind {
@@ -157,8 +240,21 @@ enum PartOfSpeechKind {
     Eof,
 }
 
+impl PartOfSpeechKind {
+    /// Name to show the player in error messages.
+    fn name(self) -> &'static str {
+        match self {
+            PartOfSpeechKind::Start => "start",
+            PartOfSpeechKind::Collator => "collator",
+            PartOfSpeechKind::Verb => "verb",
+            PartOfSpeechKind::Noun => "noun",
+            PartOfSpeechKind::Eof => "nothing",
+        }
+    }
+}
+
 impl TryFrom<Option<&PartOfSpeech>> for PartOfSpeechKind {
-    type Error = anyhow::Error;
+    type Error = &'static str;
     fn try_from(value: Option<&PartOfSpeech>) -> Result<Self, Self::Error> {
         Ok(match value {
             Some(PartOfSpeech::ParticleStart) => PartOfSpeechKind::Start,
@@ -166,10 +262,7 @@ impl TryFrom<Option<&PartOfSpeech>> for PartOfSpeechKind {
             Some(PartOfSpeech::Noun { depth: 0, .. }) => PartOfSpeechKind::Noun,
             Some(PartOfSpeech::Verb { depth: 0, .. }) => PartOfSpeechKind::Verb,
             None => PartOfSpeechKind::Eof,
-            Some(oh_no) => bail!(
-                "could not convert {:?} because it had a non-zero depth",
-                oh_no
-            ),
+            Some(_) => return Err("modifiers can't go on the spine"),
         })
     }
 }
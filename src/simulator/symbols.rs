@@ -11,7 +11,7 @@ use cogs_gamedev::grids::{Direction4, ICoord};
 use itertools::Itertools;
 use macroquad::prelude::{Color, Image, Rect, Texture2D, Vec2};
 
-use crate::assets::Assets;
+use crate::assets::RenderAssets;
 
 pub const SYMBOL_SIZE: usize = 5;
 pub const SYMBOL_DISPLAY_SIZE: f32 = 15.0;
@@ -35,43 +35,44 @@ impl Symbol {
     /// Number of symbols that fit in the atlas per row. (And per column.)
     pub const SYMBOLS_PER_ROW: u16 = Symbol::ATLAS_SIDE / 5;
 
-    /// Stitch a bunch of symbol codes into the atlas texture.
+    /// Give each of a bunch of symbol codes its own index into the atlas texture.
     ///
     /// The 5x5 symbols are packed into a 256x256 texture.
     /// (This does mean there can only be `(256 / 5) ^ 2 = 2601` unique symbols,
     /// but I question what you're doing with all those.)
     ///
-    /// Also returns a mapping of symbol codes to their index into the texture.
-    pub fn stitch_atlas(codes: impl Iterator<Item = u32>, assets: &Assets) -> AHashMap<u32, usize> {
+    /// This doesn't touch the texture; `RenderAssets::stitch_atlas` does that when drawing.
+    pub fn atlas_indices(codes: impl Iterator<Item = u32>) -> AHashMap<u32, usize> {
+        codes
+            .unique()
+            .enumerate()
+            .map(|(idx, code)| (code, idx))
+            .collect()
+    }
+
+    /// Draw each symbol code at its index, for putting in the atlas texture.
+    pub fn atlas_image(indices: &AHashMap<u32, usize>) -> Image {
         let mut img =
             Image::gen_image_color(Self::ATLAS_SIDE, Self::ATLAS_SIDE, macroquad::color::BLANK);
 
-        let out = codes
-            .unique()
-            .enumerate()
-            .map(|(idx, code)| {
-                let rect = Self::slice(idx);
-                for dx in 0..SYMBOL_SIZE {
-                    for dy in 0..SYMBOL_SIZE {
-                        let px = rect.x as usize + dx;
-                        let py = rect.y as usize + dy;
-
-                        let bitpos = SYMBOL_SIZE * dy + dx;
-                        let bit = code & (1 << (24 - bitpos));
-                        if bit != 0 {
-                            // Draw it in white so we can change it to any color
-                            img.set_pixel(px as u32, py as u32, macroquad::color::WHITE);
-                        }
+        for (&code, &idx) in indices {
+            let rect = Self::slice(idx);
+            for dx in 0..SYMBOL_SIZE {
+                for dy in 0..SYMBOL_SIZE {
+                    let px = rect.x as usize + dx;
+                    let py = rect.y as usize + dy;
+
+                    let bitpos = SYMBOL_SIZE * dy + dx;
+                    let bit = code & (1 << (24 - bitpos));
+                    if bit != 0 {
+                        // Draw it in white so we can change it to any color
+                        img.set_pixel(px as u32, py as u32, macroquad::color::WHITE);
                     }
                 }
+            }
+        }
 
-                (code, idx)
-            })
-            .collect();
-
-        assets.symbol_atlas.update(&img);
-
-        out
+        img
     }
 
     /// Get the rectangle needed to slice out this symbol's texture from the atlas.
@@ -87,7 +88,15 @@ impl Symbol {
         }
     }
 
-    pub fn draw(&self, corner: Vec2, idx: usize, color: Color, assets: &Assets) {
+    /// Draw this symbol `size` pixels across.
+    pub fn draw_sized(
+        &self,
+        corner: Vec2,
+        size: f32,
+        idx: usize,
+        color: Color,
+        assets: &RenderAssets,
+    ) {
         use macroquad::prelude::*;
         draw_texture_ex(
             assets.symbol_atlas,
@@ -96,7 +105,7 @@ impl Symbol {
             color,
             DrawTextureParams {
                 source: Some(Symbol::slice(idx)),
-                dest_size: Some(Vec2::splat(SYMBOL_DISPLAY_SIZE)),
+                dest_size: Some(Vec2::splat(size)),
                 ..Default::default()
             },
         );
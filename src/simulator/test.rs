@@ -2,7 +2,9 @@
 
 use std::str::FromStr;
 
-use super::symbols::*;
+use cogs_gamedev::grids::{Direction4, ICoord};
+
+use super::{board::Board, levels::RawLevel, symbols::*};
 
 #[test]
 fn parse_parts_of_speech() {
@@ -52,3 +54,145 @@ fn parse_parts_of_speech() {
         assert_eq!(res, *expect, "testing idx {}: \n{}", idx, test);
     }
 }
+
+#[test]
+fn explain_bad_modifier() {
+    let raw: RawLevel = toml::from_str(
+        r#"
+name = "Bad Adjective"
+board = "@NV\n A"
+
+[symbols]
+"@" = "XXXXX\nX   X\nX   X\nX   X\nXXXXX"
+N = "XX XX\nX   X\nXXXXX\nX   X\nXX XX"
+V = "X XXX\nX    \nX XXX\nX   X\nXXXXX"
+A = " XXXX\nXX  X\nX X X\nX  XX\nXXXX "
+"#,
+    )
+    .unwrap();
+    let board = raw
+        .to_level(String::from("bad_adjective"))
+        .unwrap()
+        .original_board;
+    let report = board.check_grammar();
+
+    assert_eq!(report.sentences.len(), 1);
+    let sentence = &report.sentences[0];
+    assert_eq!(
+        sentence.spine,
+        vec![ICoord::new(0, 0), ICoord::new(1, 0), ICoord::new(2, 0)]
+    );
+
+    let oh_no = sentence.result.as_ref().unwrap_err();
+    assert_eq!(oh_no.pos, Some(ICoord::new(1, 1)));
+    assert_eq!(oh_no.reason, "adjective has 2 islands, noun has 1");
+}
+
+/// Board with a 3x2 block of the same symbol in one fragment and a lone one off to the side.
+fn glue_cut_board() -> Board {
+    let raw: RawLevel = toml::from_str(
+        r#"
+name = "Glue and Cut"
+board = "XXX\nXXX X"
+
+[symbols]
+"X" = "XX XX\nX   X\nXXXXX\nX   X\nXX XX"
+"#,
+    )
+    .unwrap();
+    raw.to_level(String::from("glue_cut"))
+        .unwrap()
+        .original_board
+}
+
+#[test]
+fn glue_fragments() {
+    let mut board = glue_cut_board();
+    assert_eq!(board.fragments.len(), 2);
+
+    // Not touching
+    assert!(board.glue(ICoord::new(2, 1), ICoord::new(4, 1)).is_err());
+    // Same fragment
+    assert!(board.glue(ICoord::new(0, 0), ICoord::new(1, 0)).is_err());
+
+    board.cut(ICoord::new(1, 0), Direction4::East).unwrap();
+    assert_eq!(board.fragments.len(), 3);
+    board.glue(ICoord::new(1, 1), ICoord::new(2, 1)).unwrap();
+    assert_eq!(board.fragments.len(), 2);
+    assert_eq!(
+        board.fragment_at(ICoord::new(0, 0)),
+        board.fragment_at(ICoord::new(2, 0))
+    );
+}
+
+#[test]
+fn cut_fragments() {
+    let mut board = glue_cut_board();
+
+    // Not in the same fragment
+    assert!(board.cut(ICoord::new(2, 1), Direction4::East).is_err());
+
+    // Cut between the top and bottom rows
+    board.cut(ICoord::new(0, 0), Direction4::South).unwrap();
+    assert_eq!(board.fragments.len(), 3);
+    let top = board.fragment_at(ICoord::new(0, 0)).unwrap();
+    assert_eq!(board.fragments[top].len(), 3);
+    assert_eq!(board.fragment_at(ICoord::new(2, 0)), Some(top));
+    assert_ne!(board.fragment_at(ICoord::new(0, 1)), Some(top));
+
+    // Every symbol is still in exactly one fragment
+    let covered: usize = board.fragments.iter().map(|frag| frag.len()).sum();
+    assert_eq!(covered, board.symbols.len());
+}
+
+#[test]
+fn save_and_load_board() {
+    let original = glue_cut_board();
+    let mut board = original.clone();
+    board.cut(ICoord::new(0, 0), Direction4::South).unwrap();
+    let loaded = Board::load(&board.save(), &original).unwrap();
+    assert_eq!(loaded.symbols, board.symbols);
+    assert_eq!(loaded.fragments, board.fragments);
+
+    // The level got a new symbol since the board was saved
+    let mut changed = original.clone();
+    let pos = ICoord::new(6, 0);
+    changed.symbols.insert(
+        pos,
+        Symbol::from_str(" ### \n#   #\n#   #\n#   #\n ### ").unwrap(),
+    );
+    changed.fragments.push(std::iter::once(pos).collect());
+    assert!(Board::load(&board.save(), &changed).is_err());
+}
+
+#[test]
+fn dump_level_round_trip() {
+    let raw: RawLevel = toml::from_str(
+        r#"
+name = "Dump Me"
+board = "\n @X\n  X"
+glues = 2
+
+[symbols]
+"@" = "XXXXX\nX   X\nX   X\nX   X\nXXXXX"
+X = "XX XX\nX   X\nXXXXX\nX   X\nXX XX"
+
+[reveal]
+islands = true
+"#,
+    )
+    .unwrap();
+    let level = raw.to_level(String::from("dump_me")).unwrap();
+    let mut board = level.original_board.clone();
+    // Move the lone X somewhere else
+    let x = board.symbols.remove(&ICoord::new(2, 2)).unwrap();
+    board.symbols.insert(ICoord::new(5, 7), x);
+
+    let dumped = level.dump(&board, 1, 0).unwrap();
+    let raw: RawLevel = toml::from_str(&dumped).unwrap();
+    let again = raw.to_level(String::from("dump_me")).unwrap();
+    assert_eq!(again.original_board.symbols, board.symbols);
+    assert_eq!(again.glues, 1);
+    assert_eq!((again.width, again.height), (level.width, level.height));
+    assert!(again.reveal.islands);
+}
@@ -0,0 +1,28 @@
+//! Copying and pasting text.
+//!
+//! The clipboard belongs to miniquad, which only lives on the main thread,
+//! so the threaded game loop doesn't get one.
+
+#[cfg(any(target_arch = "wasm32", not(feature = "thread_loop")))]
+pub fn copy(text: &str) -> bool {
+    // Safety: updates happen on the main thread in this loop, same as macroquad
+    let gl = unsafe { macroquad::window::get_internal_gl() };
+    macroquad::miniquad::clipboard::set(gl.quad_context, text);
+    true
+}
+
+#[cfg(any(target_arch = "wasm32", not(feature = "thread_loop")))]
+pub fn paste() -> Option<String> {
+    let gl = unsafe { macroquad::window::get_internal_gl() };
+    macroquad::miniquad::clipboard::get(gl.quad_context)
+}
+
+#[cfg(not(any(target_arch = "wasm32", not(feature = "thread_loop"))))]
+pub fn copy(_text: &str) -> bool {
+    false
+}
+
+#[cfg(not(any(target_arch = "wasm32", not(feature = "thread_loop"))))]
+pub fn paste() -> Option<String> {
+    None
+}
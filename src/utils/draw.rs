@@ -1,4 +1,4 @@
-use crate::{ASPECT_RATIO, HEIGHT, WIDTH};
+use crate::{utils::settings, ASPECT_RATIO, HEIGHT, WIDTH};
 
 use macroquad::prelude::*;
 
@@ -8,15 +8,44 @@ pub fn hexcolor(code: u32) -> Color {
     Color::from_rgba(r, g, b, a)
 }
 
+/// A camera for drawing to a canvas-sized render target, with the origin in the top left.
+pub fn canvas_camera(target: RenderTarget) -> Camera2D {
+    // These divides and multiplies are required to get the camera in the center of the screen
+    // and having it fill everything.
+    Camera2D {
+        render_target: Some(target),
+        zoom: vec2(WIDTH.recip() * 2.0, HEIGHT.recip() * 2.0),
+        target: vec2(WIDTH / 2.0, HEIGHT / 2.0),
+        ..Default::default()
+    }
+}
+
 pub fn mouse_position_pixel() -> (f32, f32) {
     let (mx, my) = mouse_position();
+    screen_to_pixel(mx, my)
+}
+
+/// Turn a position on the window into a position on the canvas.
+pub fn screen_to_pixel(x: f32, y: f32) -> (f32, f32) {
     let (wd, hd) = width_height_deficit();
-    let mx = (mx - wd / 2.0) / ((screen_width() - wd) / WIDTH);
-    let my = (my - hd / 2.0) / ((screen_height() - hd) / HEIGHT);
-    (mx, my)
+    let x = (x - wd / 2.0) / ((screen_width() - wd) / WIDTH);
+    let y = (y - hd / 2.0) / ((screen_height() - hd) / HEIGHT);
+    (x, y)
 }
 
 pub fn width_height_deficit() -> (f32, f32) {
+    if settings::integer_scale() {
+        // Only whole numbers, but never smaller than 1x even if it doesn't fit
+        let scale = (screen_width() / WIDTH)
+            .min(screen_height() / HEIGHT)
+            .floor()
+            .max(1.0);
+        return (
+            screen_width() - WIDTH * scale,
+            screen_height() - HEIGHT * scale,
+        );
+    }
+
     if (screen_width() / screen_height()) > ASPECT_RATIO {
         // it's too wide! put bars on the sides!
         // the height becomes the authority on how wide to draw
@@ -0,0 +1,158 @@
+//! A list of text entries the player can pick from with the mouse or keyboard.
+
+use cogs_gamedev::controls::InputHandler;
+use macroquad::prelude::{vec2, Color, Rect, Texture2D, Vec2, WHITE};
+
+use std::ops::Range;
+
+use crate::{
+    controls::{Control, InputSubscriber},
+    utils::{
+        draw::hexcolor,
+        text::{self, Markup},
+    },
+};
+
+/// Height of each entry in pixels.
+const LINE_HEIGHT: f32 = 10.0;
+
+#[derive(Debug, Clone)]
+pub struct Menu {
+    pub entries: Vec<String>,
+    /// Index of the highlighted entry.
+    pub selected: usize,
+    /// Upper-left corner of the first entry.
+    pub corner: Vec2,
+    /// How wide each entry's clickable area is.
+    pub width: f32,
+    /// How many entries fit on screen at once. The rest scroll.
+    pub rows: usize,
+    /// Index of the entry at the top.
+    scroll: usize,
+
+    /// Where the mouse was last update, so it only steals the highlight when it moves.
+    last_mouse: Vec2,
+}
+
+impl Menu {
+    pub fn new(entries: Vec<String>, corner: Vec2, width: f32) -> Self {
+        Self {
+            entries,
+            selected: 0,
+            corner,
+            width,
+            rows: usize::MAX,
+            scroll: 0,
+            last_mouse: Vec2::ZERO,
+        }
+    }
+
+    /// Only show this many entries at once and scroll the rest.
+    pub fn with_rows(self, rows: usize) -> Self {
+        Self { rows, ..self }
+    }
+
+    /// Move the highlight around.
+    ///
+    /// Returns the index of the entry the player picked this update, if any.
+    pub fn update(&mut self, controls: &InputSubscriber) -> Option<usize> {
+        let len = self.entries.len();
+        if len == 0 {
+            return None;
+        }
+
+        if controls.clicked_down(Control::Up) {
+            self.selected = (self.selected + len - 1) % len;
+        }
+        if controls.clicked_down(Control::Down) {
+            self.selected = (self.selected + 1) % len;
+        }
+        // The scroll wheel zooms by default, so let those scroll menus too
+        let max_scroll = len.saturating_sub(self.rows);
+        if controls.clicked_down(Control::ZoomIn) {
+            self.scroll = self.scroll.saturating_sub(1);
+            self.selected = self.selected.min(self.scroll.saturating_add(self.rows - 1));
+        }
+        if controls.clicked_down(Control::ZoomOut) {
+            self.scroll = (self.scroll + 1).min(max_scroll);
+            self.selected = self.selected.max(self.scroll);
+        }
+        // Keep the highlight on screen
+        self.scroll = self
+            .scroll
+            .clamp(self.selected.saturating_sub(self.rows - 1), self.selected)
+            .min(max_scroll);
+
+        let (mx, my) = controls.mouse_pixel();
+        let mouse = vec2(mx, my);
+        let hovered = self.entry_at(mouse);
+        if mouse != self.last_mouse {
+            if let Some(hovered) = hovered {
+                self.selected = hovered;
+            }
+        }
+        self.last_mouse = mouse;
+
+        if controls.clicked_down(Control::Click) {
+            hovered.inspect(|idx| {
+                self.selected = *idx;
+            })
+        } else if controls.clicked_down(Control::Select) {
+            Some(self.selected)
+        } else {
+            None
+        }
+    }
+
+    /// The entry under this pixel.
+    pub fn entry_at(&self, pos: Vec2) -> Option<usize> {
+        self.shown().find(|idx| self.entry_rect(*idx).contains(pos))
+    }
+
+    /// Indices of the entries on screen.
+    fn shown(&self) -> Range<usize> {
+        self.scroll
+            ..self
+                .entries
+                .len()
+                .min(self.scroll.saturating_add(self.rows))
+    }
+
+    fn entry_rect(&self, idx: usize) -> Rect {
+        Rect::new(
+            self.corner.x,
+            self.corner.y + (idx - self.scroll) as f32 * LINE_HEIGHT,
+            self.width,
+            LINE_HEIGHT,
+        )
+    }
+
+    /// Draw the entries, with the highlighted one in `highlight`.
+    pub fn draw(&self, font: Texture2D, color: Color, highlight: Color) {
+        let shown = self.shown();
+        let (first, end) = (shown.start, shown.end);
+        for idx in shown {
+            let entry = &self.entries[idx];
+            let rect = self.entry_rect(idx);
+            let (prefix, color) = if idx == self.selected {
+                ("> ", highlight)
+            } else if (idx == first && first > 0) || (idx + 1 == end && end < self.entries.len()) {
+                // Hint that there's more to scroll to
+                (".:", color)
+            } else {
+                ("  ", color)
+            };
+            text::draw_wrapped(
+                &format!("{}{}", prefix, entry),
+                vec2(rect.x, rect.y + 1.0),
+                f32::INFINITY,
+                Markup::plain(font, color),
+            );
+        }
+    }
+
+    /// Draw the entries in white with a yellow highlight.
+    pub fn draw_default(&self, font: Texture2D) {
+        self.draw(font, WHITE, hexcolor(0xfbf236ff));
+    }
+}
@@ -1,4 +1,7 @@
+pub mod clipboard;
 pub mod draw;
+pub mod menu;
 pub mod profile;
 pub mod serdeflate;
+pub mod settings;
 pub mod text;
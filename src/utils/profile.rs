@@ -1,56 +1,310 @@
+mod migrate;
+mod slots;
+pub use slots::{Slots, MAX_NAME_LEN};
+
+use std::sync::{Mutex, MutexGuard};
+
+use ahash::AHashMap;
+use anyhow::Context;
 use macroquad::prelude::warn;
 use quad_wasmnastics::storage::{self, Location};
 use serde::{Deserialize, Serialize};
 
-const SERIALIZATION_VERSION: &str = "0";
+use crate::{
+    controls::{Control, InputCode},
+    simulator::board::SavedBoard,
+    utils::{serdeflate, settings::Settings},
+};
 
-/// Profile information. The `get` function loads it from storage; on drop it saves it back.
-#[derive(Serialize, Deserialize)]
+/// Where profiles were saved before they had version numbers, as a bare bincoded `Profile`.
+const LEGACY_STORAGE_VERSION: &str = "0";
+/// Where profiles are saved now, wrapped in an `Envelope`.
+const STORAGE_VERSION: &str = "1";
+/// Which layout of `Profile` this version of the game saves.
+///
+/// Bump this whenever `Profile` or anything in it changes; see the `migrate` module.
+pub const PROFILE_VERSION: u32 = 1;
+
+/// The profile that's currently loaded, loaded from storage the first time it's used.
+#[cfg(not(test))]
+static CURRENT: once_cell::sync::Lazy<Mutex<Profile>> =
+    once_cell::sync::Lazy::new(|| Mutex::new(Profile::load(Slots::current().active)));
+
+// Each test gets a fresh profile of its own, so tests running at once can't see each other's progress
+#[cfg(test)]
+thread_local! {
+    static CURRENT: &'static Mutex<Profile> = Box::leak(Box::new(Mutex::new(Profile::default())));
+}
+
+/// Profile information. The `load` function loads it from storage.
+///
+/// Nothing saves it automatically; call `save` after changing something that matters.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Profile {
+    /// Which save slot this goes in. It's not saved, since it's where it's saved.
+    #[serde(skip)]
+    pub slot: u32,
     pub open_count: u64,
+    /// How the player has done on each level, keyed by `Level::id`.
+    pub levels: AHashMap<String, LevelRecord>,
+    /// How the player changed the default bindings. `Some` binds an input to a control,
+    /// and `None` unbinds an input the defaults use. See `InputSubscriber::with_overrides`.
+    pub controls: AHashMap<InputCode, Option<Control>>,
+    pub settings: Settings,
 }
 
 impl Default for Profile {
     fn default() -> Self {
-        Profile { open_count: 0 }
+        Profile {
+            slot: 0,
+            open_count: 0,
+            levels: AHashMap::new(),
+            controls: AHashMap::new(),
+            settings: Settings::default(),
+        }
     }
 }
 
 impl Profile {
-    pub fn get() -> Profile {
-        let maybe_profile: anyhow::Result<Profile> = try {
-            // note we save the raw bincode! it's already gzipped!
-            // if we gzipped it here it would jut be gzipped twice
-            let data = storage::load_from(&Location {
-                version: String::from(SERIALIZATION_VERSION),
-                ..Default::default()
-            })?;
-            bincode::deserialize(&data)?
+    /// Load the profile in this save slot.
+    pub fn load(slot: u32) -> Profile {
+        // note we save the raw bincode! it's already gzipped!
+        // if we gzipped it here it would jut be gzipped twice
+        let maybe_profile: anyhow::Result<Profile> = match storage::load_from(&slot_location(slot))
+        {
+            Ok(data) => Profile::from_envelope(&data),
+            // The first slot is the profile from before there were slots,
+            // which might be from before there were envelopes too
+            Err(_) if slot == 0 => {
+                try {
+                    let data = storage::load_from(&location(LEGACY_STORAGE_VERSION))?;
+                    migrate::upgrade(0, &data)?
+                }
+            }
+            Err(oh_no) => Err(oh_no),
         };
-        match maybe_profile {
+        let profile = match maybe_profile {
             Ok(it) => it,
             Err(oh_no) => {
                 warn!("Couldn't load profile! Loading default...\n{:?}", oh_no);
                 Profile::default()
             }
-        }
+        };
+        Profile { slot, ..profile }
+    }
+
+    /// Load the profile in this save slot and make it the current one.
+    ///
+    /// This also remembers it as the last slot played.
+    pub fn switch_to(slot: u32) {
+        let profile = Profile::load(slot);
+        profile.settings.apply();
+        *Profile::current() = profile;
+        let mut slots = Slots::current();
+        slots.active = slot;
+        slots.save();
+    }
+
+    /// Swap in a whole different profile, like an imported one, and save it over this slot.
+    pub fn replace(&mut self, new: Profile) {
+        *self = Profile {
+            slot: self.slot,
+            ..new
+        };
+        self.settings.apply();
+        self.save();
+    }
+
+    /// Lock the profile that's currently loaded.
+    ///
+    /// Don't hold onto this for long; the update and draw threads both want it.
+    pub fn current() -> MutexGuard<'static, Profile> {
+        #[cfg(not(test))]
+        let current: &'static Mutex<Profile> = &CURRENT;
+        #[cfg(test)]
+        let current = CURRENT.with(|current| *current);
+        current.lock().unwrap()
     }
-}
 
-impl Drop for Profile {
-    fn drop(&mut self) {
+    /// Write this profile to storage.
+    ///
+    /// Tests don't save, so they can't clobber the real profile.
+    pub fn save(&self) {
+        if cfg!(test) {
+            return;
+        }
         let res: anyhow::Result<()> = try {
-            let data = bincode::serialize(self)?;
-            storage::save_to(
-                &data,
-                &Location {
-                    version: String::from(SERIALIZATION_VERSION),
-                    ..Default::default()
-                },
-            )?
+            let data = self.to_envelope()?;
+            storage::save_to(&data, &slot_location(self.slot))?
         };
         if let Err(oh_no) = res {
             warn!("Couldn't save profile!\n{:?}", oh_no);
         }
     }
+
+    /// Serialize this along with `PROFILE_VERSION`, so later versions of the game can read it.
+    pub fn to_envelope(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(bincode::serialize(&self.envelope()?)?)
+    }
+
+    /// Read a profile written by `to_envelope`, by any version of the game up to this one.
+    pub fn from_envelope(data: &[u8]) -> anyhow::Result<Profile> {
+        let envelope: Envelope =
+            bincode::deserialize(data).context("couldn't read the envelope")?;
+        envelope.open()
+    }
+
+    /// Turn this into a string of text the player can copy somewhere else.
+    pub fn export(&self) -> anyhow::Result<String> {
+        serdeflate::binzip64(&self.envelope()?)
+    }
+
+    /// Read a string made by `export`, maybe on another computer or an older version.
+    pub fn import(code: &str) -> anyhow::Result<Profile> {
+        let envelope: Envelope =
+            serdeflate::unbinzip64(code.trim()).context("that isn't a profile code")?;
+        envelope.open()
+    }
+
+    fn envelope(&self) -> anyhow::Result<Envelope> {
+        Ok(Envelope {
+            version: PROFILE_VERSION,
+            data: bincode::serialize(self)?,
+        })
+    }
+
+    /// Describe what replacing this profile with `new` would do, one line per difference.
+    pub fn describe_changes(&self, new: &Profile) -> Vec<String> {
+        let mut out = Vec::new();
+        let mut compare = |what: &str, old: String, new: String| {
+            if old != new {
+                out.push(format!("{}: {} -> {}", what, old, new));
+            }
+        };
+
+        let count = |profile: &Profile, check: fn(&LevelRecord) -> bool| {
+            profile
+                .levels
+                .values()
+                .filter(|record| check(record))
+                .count()
+        };
+        let solved = |record: &LevelRecord| record.solved;
+        let started = |record: &LevelRecord| record.in_progress.is_some();
+        compare(
+            "levels solved",
+            count(self, solved).to_string(),
+            count(new, solved).to_string(),
+        );
+        compare(
+            "levels in progress",
+            count(self, started).to_string(),
+            count(new, started).to_string(),
+        );
+        compare(
+            "times played",
+            self.open_count.to_string(),
+            new.open_count.to_string(),
+        );
+        let controls = |profile: &Profile| {
+            if profile.controls.is_empty() {
+                String::from("default")
+            } else {
+                String::from("custom")
+            }
+        };
+        compare("controls", controls(self), controls(new));
+        if self.settings != new.settings {
+            out.push(String::from("settings would change"));
+        }
+
+        let lost = self
+            .levels
+            .iter()
+            .filter(|(id, record)| record.solved && !new.levels.get(*id).is_some_and(solved))
+            .count();
+        if lost > 0 {
+            out.push(format!("solved levels that would be forgotten: {}", lost));
+        }
+
+        if out.is_empty() {
+            out.push(String::from("it's the same as the profile you have now"));
+        }
+        out
+    }
+}
+
+/// What actually goes in storage: a serialized profile, and which layout of `Profile` it is.
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    version: u32,
+    data: Vec<u8>,
+}
+
+impl Envelope {
+    /// Get the profile out, bringing it up to date.
+    fn open(self) -> anyhow::Result<Profile> {
+        migrate::upgrade(self.version, &self.data)
+            .with_context(|| format!("couldn't read a version {} profile", self.version))
+    }
+}
+
+fn location(version: &str) -> Location {
+    Location {
+        version: String::from(version),
+        ..Default::default()
+    }
+}
+
+/// Where the profile in this save slot goes.
+fn slot_location(slot: u32) -> Location {
+    // The first slot is where the only profile went before there were slots
+    if slot == 0 {
+        location(STORAGE_VERSION)
+    } else {
+        location(&format!("{}-{}", STORAGE_VERSION, slot))
+    }
+}
+
+/// The player's history with one level.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LevelRecord {
+    pub solved: bool,
+    /// When it was first solved, in seconds since the Unix epoch.
+    pub first_solved: Option<f64>,
+    /// Fewest moves it's been solved in. Undone moves don't count.
+    pub best_moves: Option<u32>,
+    /// Fastest it's been solved, in seconds.
+    pub best_time: Option<f32>,
+    /// How many times the explain-the-error overlay was turned on, over every solve.
+    pub hints_used: u32,
+    /// Where the player left off, if they left before solving it.
+    pub in_progress: Option<InProgress>,
+}
+
+/// A level the player was partway through.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InProgress {
+    pub board: SavedBoard,
+    pub glues_left: u32,
+    pub cuts_left: u32,
+    /// Moves made so far. These stick around, but the undo history doesn't.
+    pub moves: u32,
+    pub time_spent: f32,
+    pub hints_used: u32,
+}
+
+impl LevelRecord {
+    /// Write down a solve, keeping the best scores.
+    pub fn record_solve(&mut self, moves: u32, time: f32, hints: u32) {
+        if !self.solved {
+            self.solved = true;
+            self.first_solved = Some(macroquad::miniquad::date::now());
+        }
+        self.best_moves = Some(self.best_moves.map_or(moves, |best| best.min(moves)));
+        self.best_time = Some(self.best_time.map_or(time, |best| best.min(time)));
+        self.hints_used += hints;
+    }
 }
+
+mod test;
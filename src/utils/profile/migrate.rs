@@ -0,0 +1,50 @@
+//! Bringing profiles saved by older versions of the game up to date.
+//!
+//! bincode doesn't write down field names, so once `Profile` changes shape the old shape
+//! has to stick around in here to read old saves with. To change `Profile`:
+//!
+//! 1. Copy the current `Profile`, and anything in it that's about to change,
+//!    into a module here named after the current `PROFILE_VERSION`.
+//! 2. Bump `PROFILE_VERSION` and make the change.
+//! 3. Write a function turning the old version into the new one and add it to `upgrade`.
+//! 4. Save a profile from the old version into `fixtures/` and test it loads.
+
+use anyhow::bail;
+use serde::Deserialize;
+
+use super::{Profile, PROFILE_VERSION};
+
+/// Read a profile serialized as `version` and turn it into the current `Profile`,
+/// one version at a time.
+pub(super) fn upgrade(version: u32, data: &[u8]) -> anyhow::Result<Profile> {
+    if version > PROFILE_VERSION {
+        bail!(
+            "it's from a newer version of the game, which only knows up to version {}",
+            PROFILE_VERSION
+        );
+    }
+
+    // Each step picks up whatever the step before made, or reads it fresh if it's that new.
+    let v1 = match version {
+        0 => v0_to_v1(bincode::deserialize(data)?),
+        _ => bincode::deserialize(data)?,
+    };
+    Ok(v1)
+}
+
+mod v0 {
+    use super::*;
+
+    /// The profile from the jam, which only counted how many times the game was opened.
+    #[derive(Deserialize)]
+    pub struct Profile {
+        pub open_count: u64,
+    }
+}
+
+fn v0_to_v1(old: v0::Profile) -> Profile {
+    Profile {
+        open_count: old.open_count,
+        ..Profile::default()
+    }
+}
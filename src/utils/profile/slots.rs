@@ -0,0 +1,167 @@
+//! Letting more than one person play on the same computer.
+//!
+//! Each slot has its own whole `Profile`, saved in its own place.
+//! The list of slots is saved on its own.
+
+use std::sync::{Mutex, MutexGuard};
+
+use anyhow::{anyhow, bail};
+use macroquad::prelude::warn;
+use once_cell::sync::Lazy;
+use quad_wasmnastics::storage;
+use serde::{Deserialize, Serialize};
+
+use super::{location, slot_location, Profile};
+
+/// Where the list of slots is saved.
+const SLOTS_STORAGE_VERSION: &str = "slots";
+/// Longest name a slot can have, so it fits on the picker.
+pub const MAX_NAME_LEN: usize = 16;
+
+static CURRENT: Lazy<Mutex<Slots>> = Lazy::new(|| Mutex::new(Slots::get()));
+
+/// Every save slot, and which one was played last.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Slots {
+    pub slots: Vec<Slot>,
+    /// ID of the slot played last, which is loaded to start with.
+    pub active: u32,
+    /// ID to give the next new slot.
+    /// These never get reused, so nothing left over from a deleted slot can show up again.
+    next_id: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Slot {
+    pub id: u32,
+    pub name: String,
+}
+
+impl Default for Slots {
+    fn default() -> Self {
+        // Slot 0 is wherever the profile was before there were slots
+        Slots {
+            slots: vec![Slot {
+                id: 0,
+                name: String::from("player 1"),
+            }],
+            active: 0,
+            next_id: 1,
+        }
+    }
+}
+
+impl Slots {
+    fn get() -> Slots {
+        let maybe_slots: anyhow::Result<Slots> = try {
+            let data = storage::load_from(&location(SLOTS_STORAGE_VERSION))?;
+            bincode::deserialize(&data)?
+        };
+        match maybe_slots {
+            Ok(it) => it,
+            Err(oh_no) => {
+                warn!(
+                    "Couldn't load save slots! Starting with one...\n{:?}",
+                    oh_no
+                );
+                Slots::default()
+            }
+        }
+    }
+
+    /// Lock the list of slots.
+    ///
+    /// Don't lock `Profile::current` while holding this;
+    /// the first time it's locked it locks this to find out which slot to load.
+    pub fn current() -> MutexGuard<'static, Slots> {
+        CURRENT.lock().unwrap()
+    }
+
+    /// Write the list of slots to storage.
+    ///
+    /// Tests don't save, so they can't clobber the player's real slots.
+    pub fn save(&self) {
+        if cfg!(test) {
+            return;
+        }
+        let res: anyhow::Result<()> = try {
+            let data = bincode::serialize(self)?;
+            storage::save_to(&data, &location(SLOTS_STORAGE_VERSION))?
+        };
+        if let Err(oh_no) = res {
+            warn!("Couldn't save the save slots!\n{:?}", oh_no);
+        }
+    }
+
+    /// Make a new slot with a fresh profile in it, and return its ID.
+    pub fn create(&mut self, name: &str) -> anyhow::Result<u32> {
+        let name = self.check_name(name, None)?;
+        let id = self.next_id;
+        self.next_id += 1;
+        self.slots.push(Slot { id, name });
+        Profile {
+            slot: id,
+            ..Profile::default()
+        }
+        .save();
+        self.save();
+        Ok(id)
+    }
+
+    pub fn rename(&mut self, id: u32, name: &str) -> anyhow::Result<()> {
+        let name = self.check_name(name, Some(id))?;
+        self.get_mut(id)?.name = name;
+        self.save();
+        Ok(())
+    }
+
+    /// Get rid of a slot and everything saved in it.
+    ///
+    /// If it was the active one, the active one becomes the first slot left,
+    /// but loading that is up to the caller.
+    pub fn delete(&mut self, id: u32) -> anyhow::Result<()> {
+        if self.slots.len() <= 1 {
+            bail!("can't delete the only profile");
+        }
+        self.get_mut(id)?;
+        self.slots.retain(|slot| slot.id != id);
+        if self.active == id {
+            self.active = self.slots[0].id;
+        }
+
+        // There's no deleting from storage, so blank it out instead
+        if !cfg!(test) {
+            if let Err(oh_no) = storage::save_to([], &slot_location(id)) {
+                warn!("Couldn't clear out slot {}!\n{:?}", id, oh_no);
+            }
+        }
+        self.save();
+        Ok(())
+    }
+
+    fn get_mut(&mut self, id: u32) -> anyhow::Result<&mut Slot> {
+        self.slots
+            .iter_mut()
+            .find(|slot| slot.id == id)
+            .ok_or_else(|| anyhow!("there's no slot {}", id))
+    }
+
+    /// Tidy up a name, making sure it isn't blank and no other slot has it.
+    fn check_name(&self, name: &str, renaming: Option<u32>) -> anyhow::Result<String> {
+        let name = name.trim();
+        if name.is_empty() {
+            bail!("names can't be blank");
+        }
+        if name.chars().count() > MAX_NAME_LEN {
+            bail!("names can be at most {} letters", MAX_NAME_LEN);
+        }
+        if self
+            .slots
+            .iter()
+            .any(|slot| slot.name == name && Some(slot.id) != renaming)
+        {
+            bail!("there's already a profile named {}", name);
+        }
+        Ok(name.to_owned())
+    }
+}
@@ -0,0 +1,106 @@
+#![cfg(test)]
+
+use crate::{
+    controls::{Control, InputCode},
+    utils::settings::{InputMode, Theme},
+};
+
+use super::{migrate, Envelope, LevelRecord, Profile, PROFILE_VERSION};
+
+/// A profile with a bit of everything in it.
+fn well_played() -> Profile {
+    let mut profile = Profile {
+        open_count: 7,
+        ..Profile::default()
+    };
+    profile.levels.insert(
+        String::from("test1"),
+        LevelRecord {
+            solved: true,
+            best_moves: Some(4),
+            best_time: Some(12.5),
+            ..LevelRecord::default()
+        },
+    );
+    profile.levels.insert(
+        String::from("test2"),
+        LevelRecord {
+            hints_used: 2,
+            ..LevelRecord::default()
+        },
+    );
+    profile
+        .controls
+        .insert("Z".parse().unwrap(), Some(Control::Undo));
+    profile.settings.theme = Theme::Colorblind;
+    profile.settings.input_mode = InputMode::DragAndDrop;
+    profile
+}
+
+/// Checks that everything `well_played` put in is still there.
+fn check_well_played(profile: &Profile) {
+    assert_eq!(profile.open_count, 7);
+    assert_eq!(profile.settings.theme, Theme::Colorblind);
+    assert_eq!(profile.settings.input_mode, InputMode::DragAndDrop);
+
+    let solved = &profile.levels["test1"];
+    assert!(solved.solved);
+    assert_eq!(solved.best_moves, Some(4));
+    assert_eq!(solved.best_time, Some(12.5));
+
+    let unsolved = &profile.levels["test2"];
+    assert!(!unsolved.solved);
+    assert_eq!(unsolved.hints_used, 2);
+
+    let controls = &profile.controls;
+    assert_eq!(controls.len(), 1);
+    assert_eq!(
+        controls[&"Z".parse::<InputCode>().unwrap()],
+        Some(Control::Undo)
+    );
+}
+
+#[test]
+fn load_v0() {
+    // Saved bare, from before there were envelopes
+    let profile = migrate::upgrade(0, include_bytes!("fixtures/v0.bin")).unwrap();
+    assert_eq!(profile.open_count, 5);
+    assert!(profile.levels.is_empty());
+    assert!(profile.controls.is_empty());
+}
+
+#[test]
+fn envelope_round_trip() {
+    let profile = Profile::from_envelope(&well_played().to_envelope().unwrap()).unwrap();
+    check_well_played(&profile);
+}
+
+#[test]
+fn export_and_import() {
+    let code = well_played().export().unwrap();
+    // Stray whitespace from copying and pasting is fine
+    check_well_played(&Profile::import(&format!("  {}\n", code)).unwrap());
+
+    assert!(Profile::import("not a profile").is_err());
+}
+
+#[test]
+fn describe_import_changes() {
+    let profile = well_played();
+    let changes = profile.describe_changes(&Profile::default());
+    assert!(changes.contains(&String::from("levels solved: 1 -> 0")));
+    assert!(changes.contains(&String::from("controls: custom -> default")));
+    assert!(changes.contains(&String::from("solved levels that would be forgotten: 1")));
+
+    assert_eq!(profile.describe_changes(&profile).len(), 1);
+}
+
+#[test]
+fn newer_versions_are_rejected() {
+    let newer = Envelope {
+        version: PROFILE_VERSION + 1,
+        data: bincode::serialize(&Profile::default()).unwrap(),
+    };
+    let data = bincode::serialize(&newer).unwrap();
+    assert!(Profile::from_envelope(&data).is_err());
+}
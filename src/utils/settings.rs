@@ -0,0 +1,191 @@
+//! Options the player can change, saved in their profile.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use macroquad::prelude::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::draw::hexcolor;
+
+/// Volumes go from 0 up to this.
+pub const MAX_VOLUME: u8 = 10;
+
+/// Settings the main loop and drawing need all the time,
+/// kept out here so they don't have to lock the profile every frame.
+static FULLSCREEN: AtomicBool = AtomicBool::new(false);
+static INTEGER_SCALE: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Settings {
+    pub master_volume: u8,
+    pub music_volume: u8,
+    /// There aren't any sound effects yet, but when there are they'll listen to this.
+    pub sfx_volume: u8,
+    pub fullscreen: bool,
+    /// Only scale the game up by whole numbers so every pixel is the same size,
+    /// even if it leaves bigger black bars.
+    pub integer_scale: bool,
+    pub theme: Theme,
+    /// Skip animations that fly things around the screen.
+    pub reduced_motion: bool,
+    pub input_mode: InputMode,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            master_volume: MAX_VOLUME,
+            music_volume: MAX_VOLUME,
+            sfx_volume: MAX_VOLUME,
+            fullscreen: false,
+            integer_scale: false,
+            theme: Theme::Classic,
+            reduced_motion: false,
+            input_mode: InputMode::ClickTwice,
+        }
+    }
+}
+
+impl Settings {
+    /// Start using these settings.
+    pub fn apply(&self) {
+        FULLSCREEN.store(self.fullscreen, Ordering::Relaxed);
+        INTEGER_SCALE.store(self.integer_scale, Ordering::Relaxed);
+    }
+
+    /// How loud to play music, from 0 to 1.
+    pub fn music_gain(&self) -> f32 {
+        (self.master_volume as f32 / MAX_VOLUME as f32)
+            * (self.music_volume as f32 / MAX_VOLUME as f32)
+    }
+}
+
+/// Is the canvas only allowed to scale by whole numbers?
+pub fn integer_scale() -> bool {
+    INTEGER_SCALE.load(Ordering::Relaxed)
+}
+
+/// Go in or out of fullscreen if the setting changed since last time.
+///
+/// `fullscreen` is whether the window is fullscreen now.
+/// Only the main thread can touch the window, so call this from the draw loop.
+pub fn update_window(fullscreen: &mut bool) {
+    let wanted = FULLSCREEN.load(Ordering::Relaxed);
+    if wanted != *fullscreen {
+        *fullscreen = wanted;
+        // Safety: this is the main thread, where macroquad lives
+        let gl = unsafe { macroquad::window::get_internal_gl() };
+        gl.quad_context.set_fullscreen(wanted);
+    }
+}
+
+/// How picking up and putting down fragments works.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputMode {
+    /// Click to pick up and click again to put down
+    ClickTwice,
+    /// Press to pick up and let go to put down
+    DragAndDrop,
+}
+
+impl InputMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            InputMode::ClickTwice => "click twice",
+            InputMode::DragAndDrop => "drag and drop",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            InputMode::ClickTwice => InputMode::DragAndDrop,
+            InputMode::DragAndDrop => InputMode::ClickTwice,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    Classic,
+    HighContrast,
+    /// Good and bad are blue and orange instead of green and red
+    Colorblind,
+}
+
+impl Theme {
+    pub const ALL: &'static [Theme] = &[Theme::Classic, Theme::HighContrast, Theme::Colorblind];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Classic => "classic",
+            Theme::HighContrast => "high contrast",
+            Theme::Colorblind => "colorblind",
+        }
+    }
+
+    pub fn palette(self) -> Palette {
+        match self {
+            Theme::Classic => Palette {
+                square: hexcolor(0x92e8c0ff),
+                ink: hexcolor(0x14182eff),
+                good: hexcolor(0x6abe30ff),
+                bad: hexcolor(0xac3232ff),
+                highlight: hexcolor(0xfbf236ff),
+                info: hexcolor(0x5fcde4ff),
+                cut: hexcolor(0xd95763ff),
+                sentence: hexcolor(0x306082ff),
+                problem: hexcolor(0xdf7126ff),
+                scrollbar: hexcolor(0x847e87ff),
+            },
+            Theme::HighContrast => Palette {
+                square: hexcolor(0xffffffff),
+                ink: hexcolor(0x000000ff),
+                good: hexcolor(0x008000ff),
+                bad: hexcolor(0xe00000ff),
+                highlight: hexcolor(0xffff00ff),
+                info: hexcolor(0x00ffffff),
+                cut: hexcolor(0xff00ffff),
+                sentence: hexcolor(0x0000ffff),
+                problem: hexcolor(0xff8000ff),
+                scrollbar: hexcolor(0xffffffff),
+            },
+            // Okabe and Ito's colors
+            Theme::Colorblind => Palette {
+                square: hexcolor(0xe8e8e8ff),
+                ink: hexcolor(0x14182eff),
+                good: hexcolor(0x0072b2ff),
+                bad: hexcolor(0xe69f00ff),
+                highlight: hexcolor(0xf0e442ff),
+                info: hexcolor(0x56b4e9ff),
+                cut: hexcolor(0xcc79a7ff),
+                sentence: hexcolor(0x009e73ff),
+                problem: hexcolor(0xd55e00ff),
+                scrollbar: hexcolor(0x847e87ff),
+            },
+        }
+    }
+}
+
+/// Colors for drawing the board.
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    /// Behind each square
+    pub square: Color,
+    /// Symbols that aren't colored by anything else, and fragment outlines
+    pub ink: Color,
+    /// Symbols in good sentences
+    pub good: Color,
+    /// Symbols that aren't
+    pub bad: Color,
+    /// The keyboard cursor, the glue tool and whatever's picked
+    pub highlight: Color,
+    /// The inspected fragment
+    pub info: Color,
+    /// The cut tool
+    pub cut: Color,
+    /// Sentence spines in the explain overlay
+    pub sentence: Color,
+    /// Problems in the explain overlay
+    pub problem: Color,
+    pub scrollbar: Color,
+}
@@ -5,12 +5,9 @@ use macroquad::prelude::{Color, Rect, Texture2D, Vec2, WHITE};
 use once_cell::sync::Lazy;
 use regex::Regex;
 
-use crate::{
-    assets::Assets,
-    utils::{
-        draw,
-        text::{Markup, Wave},
-    },
+use crate::utils::{
+    draw,
+    text::{Markup, Wave},
 };
 
 use super::{TextSpan, CHARACTER_COUNT};
@@ -2,7 +2,9 @@
 
 mod billboard;
 pub use billboard::Billboard;
-use macroquad::prelude::{Color, Texture2D, WHITE};
+use macroquad::prelude::{draw_texture_ex, vec2, Color, DrawTextureParams, Rect, Texture2D, Vec2};
+
+use std::mem;
 
 use crate::assets;
 
@@ -64,3 +66,90 @@ pub struct Wave {
     /// A value of `5.0` means the text moves 5.0 pixels up, then 5.0 pixels down...
     pub magnitude: f32,
 }
+
+impl Markup {
+    /// Plain, unwavy text with 1 pixel of kerning and vertical space.
+    pub fn plain(font: Texture2D, color: Color) -> Self {
+        Self {
+            font,
+            color,
+            kerning: 1.0,
+            vert_space: 1.0,
+            wave: None,
+        }
+    }
+}
+
+/// Draw text with no billboard behind it, wrapping words so lines fit in `max_width` pixels.
+///
+/// `corner` is the upper-left corner of the first line.
+/// Returns the Y position just below the last line drawn, so you can stack things under it.
+pub fn draw_wrapped(text: &str, corner: Vec2, max_width: f32, markup: Markup) -> f32 {
+    let line_height = markup.font.height() + markup.vert_space;
+
+    let mut y = corner.y;
+    for line in wrap(text, max_width, markup) {
+        draw_line(&line, vec2(corner.x, y), markup);
+        y += line_height;
+    }
+
+    y
+}
+
+/// How tall `draw_wrapped` would draw this, in pixels.
+pub fn wrapped_height(text: &str, max_width: f32, markup: Markup) -> f32 {
+    wrap(text, max_width, markup).len() as f32 * (markup.font.height() + markup.vert_space)
+}
+
+/// Break text into lines that fit in `max_width` pixels.
+fn wrap(text: &str, max_width: f32, markup: Markup) -> Vec<String> {
+    let char_width = markup.font.width() / CHARACTER_COUNT as f32;
+    let advance = char_width + markup.kerning;
+    let max_chars = (((max_width + markup.kerning) / advance) as usize).max(1);
+
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for (idx, word) in paragraph.split(' ').enumerate() {
+            if idx != 0 {
+                if line.len() + 1 + word.len() > max_chars {
+                    lines.push(mem::take(&mut line));
+                } else {
+                    line.push(' ');
+                }
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Draw one line of text with its upper-left corner at `corner`.
+fn draw_line(line: &str, corner: Vec2, markup: Markup) {
+    let char_width = markup.font.width() / CHARACTER_COUNT as f32;
+    let char_height = markup.font.height();
+
+    for (idx, c) in line.bytes().enumerate() {
+        let slice_idx = match c {
+            b' '..=b'~' => (c - 0x20) as usize,
+            // otherwise just do the non-printing character
+            _ => CHARACTER_COUNT - 1,
+        };
+        draw_texture_ex(
+            markup.font,
+            corner.x + idx as f32 * (char_width + markup.kerning),
+            corner.y,
+            markup.color,
+            DrawTextureParams {
+                source: Some(Rect::new(
+                    slice_idx as f32 * char_width,
+                    0.0,
+                    char_width,
+                    char_height,
+                )),
+                ..Default::default()
+            },
+        );
+    }
+}